@@ -0,0 +1,554 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use base64::Engine;
+use chrono::{DateTime, Local};
+use flate2::bufread::{GzDecoder, MultiGzDecoder};
+
+use crate::{config::SourceKind, prelude::*};
+
+/// A page as it was archived
+#[derive(Debug, Clone)]
+pub struct Capture {
+    pub url: String,
+    pub captured: DateTime<Utc>,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Deserialize)]
+struct HarLog {
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarEntry {
+    started_date_time: String,
+    request: HarRequest,
+    response: HarResponse,
+}
+
+#[derive(Deserialize)]
+struct HarRequest {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct HarResponse {
+    status: u16,
+    content: HarContent,
+}
+
+#[derive(Deserialize)]
+struct HarContent {
+    text: Option<String>,
+    encoding: Option<String>,
+}
+
+/// A response of an archive. Its body is only decoded for the pages a source
+/// reads.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub url: String,
+    date: String,
+    body: RecordBody,
+}
+
+#[derive(Debug, Clone)]
+enum RecordBody {
+    /// the response as the server sent it
+    Http(Vec<u8>),
+    /// the text of a HAR response, base64 for binary ones
+    Har { text: String, base64: bool },
+}
+
+fn parse_capture_date(date: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.to_utc())
+        .map_err(|_| WatchError::ParseField {
+            field: "capture date",
+            value: date.to_owned(),
+        })
+}
+
+/// Successful responses of a browser HAR capture
+pub fn read_har(s: &str) -> Result<Vec<Record>> {
+    let har: Har = serde_json::from_str(s)?;
+
+    Ok(har
+        .log
+        .entries
+        .into_iter()
+        .filter_map(|entry| {
+            let (200..=299, Some(text)) = (entry.response.status, entry.response.content.text)
+            else {
+                return None;
+            };
+
+            Some(Record {
+                url: entry.request.url,
+                date: entry.started_date_time,
+                body: RecordBody::Har {
+                    text,
+                    base64: entry.response.content.encoding.as_deref() == Some("base64"),
+                },
+            })
+        })
+        .collect())
+}
+
+/// Body of a chunked transfer
+fn dechunk(mut s: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    while let Some(end) = s.windows(2).position(|w| w == b"\r\n") {
+        let size = std::str::from_utf8(&s[..end])
+            .ok()
+            .and_then(|line| usize::from_str_radix(line.split(';').next()?.trim(), 16).ok());
+
+        // the last chunk is empty
+        let Some(size) = size.filter(|&size| size > 0) else {
+            break;
+        };
+
+        let chunk = &s[end + 2..];
+
+        body.extend_from_slice(&chunk[..size.min(chunk.len())]);
+        s = chunk.get(size + 2..).unwrap_or_default();
+    }
+
+    body
+}
+
+/// Body of a successful HTTP response as the server sent it
+fn http_body(response: &[u8]) -> Result<Option<String>> {
+    let Some(end) = response.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return Ok(None);
+    };
+    let head = String::from_utf8_lossy(&response[..end]);
+
+    let status = head
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse::<u16>().ok());
+
+    // redirects and errors have no listings
+    if !status.is_some_and(|status| (200..300).contains(&status)) {
+        return Ok(None);
+    }
+
+    let header = |name: &str| {
+        head.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.trim().to_ascii_lowercase())
+    };
+
+    let mut body = response[end + 4..].to_vec();
+
+    if header("transfer-encoding").is_some_and(|e| e.contains("chunked")) {
+        body = dechunk(&body);
+    }
+
+    if header("content-encoding").is_some_and(|e| e.contains("gzip")) {
+        let mut decoded = Vec::new();
+
+        GzDecoder::new(&body[..]).read_to_end(&mut decoded)?;
+        body = decoded;
+    }
+
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+impl Record {
+    /// The page of a successful response
+    pub fn capture(&self) -> Result<Option<Capture>> {
+        let body = match &self.body {
+            RecordBody::Http(response) => http_body(response)?,
+            RecordBody::Har {
+                text,
+                base64: false,
+            } => Some(text.clone()),
+            RecordBody::Har { text, base64: true } => {
+                let body = base64::engine::general_purpose::STANDARD
+                    .decode(text)
+                    .map_err(|e| WatchError::ParseField {
+                        field: "base64 body",
+                        value: e.to_string(),
+                    })?;
+
+                Some(String::from_utf8_lossy(&body).into_owned())
+            }
+        };
+
+        body.map(|body| {
+            Ok(Capture {
+                url: self.url.clone(),
+                captured: parse_capture_date(&self.date)?,
+                body,
+            })
+        })
+        .transpose()
+    }
+}
+
+/// Response records of a WARC file, as they are read
+pub struct WarcRecords<R> {
+    reader: R,
+    line: String,
+    failed: bool,
+}
+
+impl<R: BufRead> WarcRecords<R> {
+    fn read_record(&mut self) -> Result<Option<Record>> {
+        let reader = &mut self.reader;
+        let line = &mut self.line;
+
+        loop {
+            line.clear();
+
+            if reader.read_line(line)? == 0 {
+                return Ok(None);
+            }
+
+            // records are separated by blank lines
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if !line.starts_with("WARC/") {
+                return Err(WatchError::ParseField {
+                    field: "WARC record",
+                    value: line.trim().to_owned(),
+                });
+            }
+
+            let mut headers = Vec::new();
+
+            loop {
+                line.clear();
+
+                if reader.read_line(line)? == 0 || line.trim().is_empty() {
+                    break;
+                }
+
+                if let Some((key, value)) = line.split_once(':') {
+                    headers.push((key.trim().to_ascii_lowercase(), value.trim().to_owned()));
+                }
+            }
+
+            let header = |name: &str| {
+                headers
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.as_str())
+            };
+
+            let length = header("content-length")
+                .and_then(|length| length.parse::<u64>().ok())
+                .ok_or_else(|| WatchError::ParseField {
+                    field: "WARC content length",
+                    value: header("content-length").unwrap_or_default().to_owned(),
+                })?;
+
+            // only responses are read, the rest is skipped without keeping it
+            let (Some("response"), Some(url), Some(date)) = (
+                header("warc-type"),
+                header("warc-target-uri"),
+                header("warc-date"),
+            ) else {
+                std::io::copy(&mut reader.take(length), &mut std::io::sink())?;
+                continue;
+            };
+            let mut block = Vec::new();
+
+            reader.take(length).read_to_end(&mut block)?;
+
+            return Ok(Some(Record {
+                // WARC 1.0 examples put the uri in angle brackets
+                url: url.trim_matches(['<', '>']).to_owned(),
+                date: date.to_owned(),
+                body: RecordBody::Http(block),
+            }));
+        }
+    }
+}
+
+// a broken record ends the file, there's no telling where the next one starts
+impl<R: BufRead> Iterator for WarcRecords<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let record = self.read_record().transpose();
+
+        self.failed = matches!(record, Some(Err(_)));
+
+        record
+    }
+}
+
+/// Response records of a WARC file, plain or gzipped
+pub fn read_warc(reader: impl Read + 'static) -> Result<WarcRecords<Box<dyn BufRead>>> {
+    let mut reader = BufReader::new(reader);
+
+    // a .warc.gz is a gzip member per record
+    let reader: Box<dyn BufRead> = match reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        true => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        false => Box::new(reader),
+    };
+
+    Ok(WarcRecords {
+        reader,
+        line: String::new(),
+        failed: false,
+    })
+}
+
+/// Records of a `.har` file or a WARC file, in the order they were archived
+pub fn read_archive(path: &Path) -> Result<Box<dyn Iterator<Item = Result<Record>>>> {
+    Ok(match path.extension().is_some_and(|e| e == "har") {
+        // a HAR is one JSON document
+        true => Box::new(
+            read_har(&std::fs::read_to_string(path)?)?
+                .into_iter()
+                .map(Ok),
+        ),
+        false => Box::new(read_warc(std::fs::File::open(path)?)?),
+    })
+}
+
+fn same_host(a: &str, b: &str) -> bool {
+    let host = |url: &str| {
+        reqwest::Url::parse(url).ok().and_then(|url| {
+            url.host_str()
+                .map(|h| h.trim_start_matches("www.").to_owned())
+        })
+    };
+
+    host(a).is_some() && host(a) == host(b)
+}
+
+/// The page parser archived pages of a source go through
+pub enum Route {
+    Forum(Box<dyn ForumSite>),
+    Reddit(RedditConfig, String),
+    Feed(String),
+    ProductOffers(Vec<String>),
+}
+
+/// Outcome of feeding an archive to a source
+#[derive(Debug, Default)]
+pub struct IngestReport {
+    pub pages: usize,
+    pub added: usize,
+    /// thread pages of threads missing from the archived thread lists
+    pub orphans: usize,
+    /// url and what went wrong
+    pub errors: Vec<(String, WatchError)>,
+}
+
+impl Route {
+    pub fn new(kind: &SourceKind) -> Result<Self> {
+        Ok(match kind {
+            SourceKind::RolexForums { forum_id } => Route::Forum(Box::new(VBulletinSite::new(
+                VBulletinConfig::rolex_forums(),
+                *forum_id,
+            )?)),
+            SourceKind::Vbulletin { forum_id, site } => {
+                Route::Forum(Box::new(VBulletinSite::new(site.clone(), *forum_id)?))
+            }
+            SourceKind::Xenforo { forum, site } => {
+                Route::Forum(Box::new(XenForoSite::new(site.clone(), forum)?))
+            }
+            SourceKind::Selectors { site } => {
+                Route::Forum(Box::new(SelectorSite::new(site.clone())?))
+            }
+            SourceKind::Reddit {
+                subreddit, site, ..
+            } => Route::Reddit(site.clone(), subreddit.clone()),
+            SourceKind::Feed { url, .. } => Route::Feed(url.clone()),
+            SourceKind::ProductOffers { urls, .. } => Route::ProductOffers(urls.clone()),
+        })
+    }
+
+    /// Whether `url` is a page of the source
+    pub fn matches(&self, url: &str) -> bool {
+        match self {
+            Route::Forum(site) => site.page_of(url).is_some(),
+            Route::Reddit(config, subreddit) => {
+                let listing = RedditListing::listing_url(config, subreddit, None);
+
+                same_host(&listing, url)
+                    && reqwest::Url::parse(url).is_ok_and(|url| {
+                        url.path()
+                            .to_lowercase()
+                            .starts_with(&format!("/r/{}/", subreddit.to_lowercase()))
+                            && url.path().ends_with(".json")
+                    })
+            }
+            Route::Feed(feed) => {
+                let strip = |url: &str| url.split_once("://").map(|(_, rest)| rest.to_owned());
+
+                strip(feed) == strip(url)
+            }
+            Route::ProductOffers(urls) => urls.iter().any(|page| same_host(page, url)),
+        }
+    }
+
+    /// Adds the entries of the archived pages of the source to a database,
+    /// see `Ingest`
+    pub fn ingest(
+        &self,
+        records: impl IntoIterator<Item = Record>,
+        data: &mut PriceDatabase<PriceEntry>,
+    ) -> IngestReport {
+        let mut ingest = Ingest::new(self, data);
+
+        for record in records {
+            ingest.add(&record);
+        }
+
+        ingest.finish()
+    }
+}
+
+/// Entries of archived pages going into a database. Entries already in it are
+/// newer than any archive and left alone, among the archived ones a later
+/// capture wins.
+pub struct Ingest<'a> {
+    route: &'a Route,
+    data: &'a mut PriceDatabase<PriceEntry>,
+    /// ids of the entries added and the time of their capture
+    archived: HashMap<u64, i64>,
+    /// latest capture of each thread page
+    threads: HashMap<u64, Capture>,
+    report: IngestReport,
+}
+
+impl<'a> Ingest<'a> {
+    pub fn new(route: &'a Route, data: &'a mut PriceDatabase<PriceEntry>) -> Self {
+        Self {
+            route,
+            data,
+            archived: HashMap::new(),
+            threads: HashMap::new(),
+            report: IngestReport::default(),
+        }
+    }
+
+    fn merge(&mut self, entry: PriceEntry, captured: i64) {
+        match self.data.entries.iter_mut().find(|e| e.id == entry.id) {
+            Some(e) => {
+                if let Some(archived) = self.archived.get_mut(&entry.id) {
+                    if *archived <= captured {
+                        *archived = captured;
+                        *e = entry;
+                    }
+                }
+            }
+            None => {
+                self.archived.insert(entry.id, captured);
+                self.data.entries.push(entry);
+            }
+        }
+    }
+
+    /// Adds the entries of a record if it's a page of the source. A record
+    /// that can't be read is an error of the report.
+    pub fn add(&mut self, record: &Record) {
+        if !self.route.matches(&record.url) {
+            return;
+        }
+
+        let capture = match record.capture() {
+            Ok(Some(capture)) => capture,
+            Ok(None) => return,
+            Err(e) => {
+                self.report.errors.push((record.url.clone(), e));
+                return;
+            }
+        };
+
+        self.report.pages += 1;
+
+        let captured = capture.captured.timestamp();
+        let result = match self.route {
+            Route::Forum(site) => match site.page_of(&capture.url) {
+                Some(ForumPage::List(page)) => site
+                    .parse_page_at(&capture.body, page, capture.captured.with_timezone(&Local))
+                    .map(|(entries, _)| entries),
+                Some(ForumPage::Thread(id)) => {
+                    if !self
+                        .threads
+                        .get(&id)
+                        .is_some_and(|c| c.captured > capture.captured)
+                    {
+                        self.threads.insert(id, capture);
+                    }
+
+                    return;
+                }
+                None => Ok(Vec::new()),
+            },
+            Route::Reddit(config, _) => {
+                RedditListing::parse_listing(config, &capture.body).map(|(entries, _)| entries)
+            }
+            Route::Feed(_) => parse_feed(&capture.body).map(|items| {
+                let entries = items.iter().filter_map(|i| i.to_entry(captured)).collect();
+
+                // live polls don't need to read them again
+                for item in &items {
+                    self.data.seen.insert(&item.guid);
+                }
+
+                entries
+            }),
+            Route::ProductOffers(_) => Ok(page_offers(&capture.body)
+                .iter()
+                .filter_map(|offer| offer.to_entry(&capture.url, captured))
+                .collect()),
+        };
+
+        match result {
+            Ok(entries) => entries.into_iter().for_each(|e| self.merge(e, captured)),
+            Err(e) => self.report.errors.push((capture.url, e)),
+        }
+    }
+
+    /// Reads the archived threads and sorts the database. Threads are read
+    /// once every list is in, only a capture taken after the last post holds
+    /// all of it.
+    pub fn finish(mut self) -> IngestReport {
+        if let Route::Forum(site) = self.route {
+            for (id, capture) in &self.threads {
+                match self.data.entries.iter_mut().find(|e| e.id == *id) {
+                    Some(entry) if self.archived.contains_key(id) => {
+                        if capture.captured.timestamp() >= entry.timestamp {
+                            site.read_thread(&capture.body, entry);
+                        }
+                    }
+                    Some(_) => {}
+                    None => self.report.orphans += 1,
+                }
+            }
+        }
+
+        self.report.added = self.archived.len();
+        self.data.entries.sort_by_key(|x| x.timestamp);
+
+        self.report
+    }
+}
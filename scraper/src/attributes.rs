@@ -0,0 +1,212 @@
+use crate::{identify::words, prelude::*, tokenize::tokenize_watch_info};
+
+/// What a listing says about the watch besides its reference, so prices of
+/// one reference can be compared like for like
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WatchAttributes {
+    /// case diameter in mm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_size: Option<f32>,
+    /// case material, "steel" or "rose gold"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<Box<str>>,
+    /// "jubilee", "rubber" or "leather"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bracelet: Option<Box<str>>,
+    /// dial color, "blue"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dial: Option<Box<str>>,
+    /// "chronograph" or "gmt"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub complications: Vec<Box<str>>,
+}
+
+// each table maps the ways titles write a value to its name, more specific
+// values come first
+
+const MATERIALS: &[(&str, &[&str])] = &[
+    (
+        "two-tone",
+        &[
+            "two tone",
+            "bicolor",
+            "bi color",
+            "rolesor",
+            "tutone",
+            "steel and gold",
+            "steel gold",
+        ],
+    ),
+    (
+        "rose gold",
+        &[
+            "rose gold",
+            "pink gold",
+            "red gold",
+            "everose",
+            "sedna gold",
+            "king gold",
+        ],
+    ),
+    ("yellow gold", &["yellow gold"]),
+    ("white gold", &["white gold"]),
+    ("platinum", &["platinum", "pt950"]),
+    ("titanium", &["titanium"]),
+    ("ceramic", &["ceramic"]),
+    ("bronze", &["bronze"]),
+    ("carbon", &["carbon"]),
+    ("gold", &["gold", "18k", "18kt", "18ct"]),
+    ("steel", &["steel", "stainless", "ss", "oystersteel"]),
+];
+
+const BRACELETS: &[(&str, &[&str])] = &[
+    ("jubilee", &["jubilee"]),
+    ("president", &["president"]),
+    ("rubber", &["rubber", "oysterflex"]),
+    ("leather", &["leather", "alligator", "croc", "crocodile"]),
+    ("nato", &["nato"]),
+    ("mesh", &["mesh", "milanese"]),
+    ("bracelet", &["bracelet"]),
+    ("strap", &["strap"]),
+];
+
+// only a color right before "dial" is the dial's, "blue bezel" isn't
+const DIALS: &[(&str, &[&str])] = &[
+    ("reverse panda", &["reverse panda"]),
+    ("panda", &["panda"]),
+    ("ice blue", &["ice blue"]),
+    ("tiffany blue", &["tiffany", "tiffany blue"]),
+    ("mother of pearl", &["mother of pearl", "mop"]),
+    ("meteorite", &["meteorite"]),
+    ("skeleton", &["skeleton", "openworked"]),
+    ("black", &["black"]),
+    ("white", &["white"]),
+    ("blue", &["blue"]),
+    ("green", &["green"]),
+    ("silver", &["silver"]),
+    ("grey", &["grey", "gray", "slate", "rhodium"]),
+    ("champagne", &["champagne"]),
+    ("brown", &["brown", "chocolate"]),
+    ("red", &["red"]),
+    ("pink", &["pink"]),
+    ("salmon", &["salmon"]),
+    ("yellow", &["yellow"]),
+    ("orange", &["orange"]),
+    ("purple", &["purple"]),
+];
+
+const COMPLICATIONS: &[(&str, &[&str])] = &[
+    ("chronograph", &["chronograph", "chrono", "cosmograph"]),
+    ("flyback", &["flyback"]),
+    ("split seconds", &["split seconds", "rattrapante"]),
+    ("gmt", &["gmt", "dual time"]),
+    ("world time", &["world time", "worldtime", "worldtimer"]),
+    ("moon phase", &["moon phase", "moonphase"]),
+    ("annual calendar", &["annual calendar"]),
+    ("perpetual calendar", &["perpetual calendar", "qp"]),
+    ("tourbillon", &["tourbillon"]),
+    ("minute repeater", &["minute repeater", "repeater"]),
+    ("power reserve", &["power reserve"]),
+    ("alarm", &["alarm"]),
+];
+
+/// A table with its phrases as words
+type Phrases = Vec<(&'static str, Vec<Vec<String>>)>;
+
+fn phrases(table: &'static [(&'static str, &'static [&'static str])]) -> Phrases {
+    table
+        .iter()
+        .map(|(value, phrases)| {
+            let phrases = phrases
+                .iter()
+                .map(|phrase| {
+                    words(&tokenize_watch_info(phrase))
+                        .into_iter()
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .collect();
+
+            (*value, phrases)
+        })
+        .collect()
+}
+
+/// Values of a table in `words`, in table order, with where their phrase
+/// starts and ends
+fn find_all(table: &Phrases, words: &[&str]) -> Vec<(&'static str, usize, usize)> {
+    let mut found = Vec::new();
+
+    for (value, phrases) in table {
+        for phrase in phrases.iter().filter(|p| !p.is_empty()) {
+            for (start, window) in words.windows(phrase.len()).enumerate() {
+                if window == phrase.as_slice() {
+                    found.push((*value, start, start + phrase.len()));
+                }
+            }
+        }
+    }
+
+    found
+}
+
+/// Case diameter of "45mm", "38.5mm" or "40 mm"
+fn case_size(tokens: &[Box<str>]) -> Option<f32> {
+    tokens.iter().enumerate().find_map(|(i, t)| {
+        let size = match t.strip_suffix("mm") {
+            Some(size) => size,
+            None if tokens.get(i + 1).is_some_and(|next| &**next == "mm") => t,
+            None => return None,
+        };
+
+        size.replace(',', ".")
+            .parse::<f32>()
+            .ok()
+            .filter(|size| (20.0..=60.0).contains(size))
+    })
+}
+
+impl WatchAttributes {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Attributes of the watch in a listing title's tokens
+pub fn find_attributes(tokens: &[Box<str>]) -> WatchAttributes {
+    lazy_static! {
+        static ref MATERIAL_PHRASES: Phrases = phrases(MATERIALS);
+        static ref BRACELET_PHRASES: Phrases = phrases(BRACELETS);
+        static ref DIAL_PHRASES: Phrases = phrases(DIALS);
+        static ref COMPLICATION_PHRASES: Phrases = phrases(COMPLICATIONS);
+    }
+
+    let words = words(tokens);
+    let first = |table: &Phrases| find_all(table, &words).first().map(|(v, ..)| (*v).into());
+
+    let dial = find_all(&DIAL_PHRASES, &words)
+        .into_iter()
+        .filter(|&(_, _, end)| {
+            words
+                .get(end)
+                .is_some_and(|w| *w == "dial" || *w == "dials")
+        })
+        .max_by_key(|&(_, start, end)| end - start)
+        .map(|(dial, ..)| dial.into());
+
+    let mut complications: Vec<Box<str>> = Vec::new();
+
+    for (complication, ..) in find_all(&COMPLICATION_PHRASES, &words) {
+        if !complications.iter().any(|c| **c == *complication) {
+            complications.push(complication.into());
+        }
+    }
+
+    WatchAttributes {
+        case_size: case_size(tokens),
+        material: first(&MATERIAL_PHRASES),
+        bracelet: first(&BRACELET_PHRASES),
+        dial,
+        complications,
+    }
+}
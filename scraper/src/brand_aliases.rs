@@ -0,0 +1,47 @@
+/// Other ways titles name a brand: abbreviations, short forms and common
+/// misspellings. Brands are spelled as in `BRANDS`.
+pub const BRAND_ALIASES: &[(&str, &[&str])] = &[
+    (
+        "A. Lange & Söhne",
+        &["als", "lange sohne", "lange und sohne", "lange & soehne"],
+    ),
+    (
+        "Audemars Piguet",
+        &["ap", "audemars", "audemar piguet", "audemars piquet"],
+    ),
+    ("Bvlgari", &["bulgari"]),
+    ("F. P. Journe", &["fpj", "fp journe", "journe"]),
+    ("Girard-Perregaux", &["gp", "girard perregaux"]),
+    ("Grand Seiko", &["gs"]),
+    ("H. Moser & Cie", &["moser", "h moser"]),
+    ("IWC", &["iwc schaffhausen"]),
+    (
+        "Jaeger-LeCoultre",
+        &[
+            "jlc",
+            "jaeger lecoultre",
+            "jaeger le coultre",
+            "lecoultre",
+            "le coultre",
+        ],
+    ),
+    ("Panerai", &["officine panerai", "pam"]),
+    (
+        "Patek Philippe",
+        &[
+            "pp",
+            "patek",
+            "patek phillipe",
+            "patek philipe",
+            "patek phillippe",
+        ],
+    ),
+    ("Richard Mille", &["rm"]),
+    ("Rolex", &["rollie", "rolly"]),
+    ("TAG Heuer", &["tagheuer", "tag-heuer"]),
+    ("Ulysse Nardin", &["un", "ulysse"]),
+    (
+        "Vacheron Constantin",
+        &["vc", "vacheron", "vacheron constantine"],
+    ),
+];
@@ -0,0 +1,50 @@
+/// Names in `BRANDS` of the same brand as another name. Listings naming
+/// either are stored under the canonical name, the second one.
+pub const BRAND_SYNONYMS: &[(&str, &str)] = &[
+    ("Abraham-Louis Breguet", "Breguet"),
+    ("Bovet Fleurier", "Bovet"),
+    ("Heuer", "TAG Heuer"),
+];
+
+/// Sub-brands and the brand they belong to. A sub-brand stays a brand of its
+/// own, but a title naming it isn't about the parent.
+pub const BRAND_PARENTS: &[(&str, &str)] = &[
+    ("Grand Seiko", "Seiko"),
+    ("ORIENT STAR", "ORIENT"),
+    ("TUDOR", "Rolex"),
+];
+
+/// Words of brand names that titles use for other things, or that several
+/// brands share, they never name a brand by themselves
+pub const BRAND_STOP_WORDS: &[&str] = &[
+    "and",
+    "clock",
+    "co",
+    "company",
+    "de",
+    "du",
+    "fleurier",
+    "originals",
+    "steel",
+    "swiss",
+    "watch",
+    "watches",
+    "x",
+    "y",
+];
+
+/// Canonical name of a brand spelled as in `BRANDS`
+pub fn canonical_brand(brand: &str) -> &str {
+    BRAND_SYNONYMS
+        .iter()
+        .find(|(name, _)| *name == brand)
+        .map_or(brand, |(_, canonical)| canonical)
+}
+
+/// Brand a sub-brand belongs to
+pub fn parent_brand(brand: &str) -> Option<&'static str> {
+    BRAND_PARENTS
+        .iter()
+        .find(|(sub_brand, _)| *sub_brand == brand)
+        .map(|(_, parent)| *parent)
+}
@@ -0,0 +1,130 @@
+use crate::prelude::*;
+
+/// What is known about a watch reference
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ModelInfo {
+    /// model family, "Submariner"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<Box<str>>,
+    /// "Submariner Date"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Box<str>>,
+    /// case diameter in mm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_size: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<Box<str>>,
+    /// year the reference was introduced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launched: Option<u16>,
+    /// year it was discontinued, None while in production
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discontinued: Option<u16>,
+}
+
+/// A `[[watch]]` of the catalog file
+#[derive(Deserialize, Clone, Debug)]
+pub struct CatalogWatch {
+    pub brand: Box<str>,
+    pub reference: Box<str>,
+    #[serde(flatten)]
+    pub info: ModelInfo,
+}
+
+/// Known references of each brand. Listing titles write references in many
+/// ways, "126610LN", "126610-LN" or "126610 LN", so they are compared on
+/// their letters and digits only, and two adjacent tokens are also tried as
+/// one reference.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Catalog {
+    #[serde(default, rename = "watch")]
+    pub watches: Vec<CatalogWatch>,
+}
+
+/// Lowercase letters and digits of a reference
+pub(crate) fn reference_key(s: &str) -> String {
+    s.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+impl Catalog {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    /// The catalog file, empty if there is none
+    pub fn global() -> &'static Catalog {
+        lazy_static! {
+            static ref CATALOG: Catalog = match std::fs::read_to_string(crate::paths::catalog_path())
+            {
+                Ok(s) => Catalog::parse(&s).unwrap_or_else(|e| {
+                    eprintln!("Ignoring reference catalog: {e}");
+                    Catalog::default()
+                }),
+                Err(_) => Catalog::default(),
+            };
+        }
+
+        &CATALOG
+    }
+
+    /// Reference of `brand` written as one of the tokens, or two adjacent
+    /// ones. An exact match wins, then a token the reference only adds letters to, such as the
+    /// bezel code of a Rolex: "126610" for "126610LN" when no other
+    /// reference starts like it.
+    pub fn find(&self, brand: &str, tokens: &[Box<str>]) -> Option<&CatalogWatch> {
+        let watches: Vec<_> = self
+            .watches
+            .iter()
+            .filter(|w| w.brand.eq_ignore_ascii_case(brand))
+            .map(|w| (reference_key(&w.reference), w))
+            .collect();
+        // "126610 ln" is one reference written as two tokens
+        let pairs = tokens
+            .windows(2)
+            .map(|pair| reference_key(&pair[0]) + &reference_key(&pair[1]));
+        let keys: Vec<_> = tokens
+            .iter()
+            .map(|t| reference_key(t))
+            .chain(pairs)
+            .filter(|k| k.len() >= 4)
+            .collect();
+
+        let exact = keys
+            .iter()
+            .find_map(|key| watches.iter().find(|(k, _)| k == key));
+
+        if let Some((_, watch)) = exact {
+            return Some(watch);
+        }
+
+        keys.iter().find_map(|key| {
+            let mut matches = watches.iter().filter(|(k, _)| {
+                k.strip_prefix(key.as_str())
+                    .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_alphabetic()))
+            });
+
+            match (matches.next(), matches.next()) {
+                (Some((_, watch)), None) => Some(*watch),
+                _ => None,
+            }
+        })
+    }
+
+    /// Brand of the only catalog reference written exactly as one of the
+    /// tokens, for titles that leave out the brand
+    pub fn brand_of(&self, tokens: &[Box<str>]) -> Option<&str> {
+        let keys: Vec<_> = tokens.iter().map(|t| reference_key(t)).collect();
+        let mut brands = self
+            .watches
+            .iter()
+            .filter(|w| keys.contains(&reference_key(&w.reference)))
+            .map(|w| &*w.brand);
+        let brand = brands.next()?;
+
+        brands.all(|b| b == brand).then_some(brand)
+    }
+}
@@ -0,0 +1,584 @@
+use std::path::{Path, PathBuf};
+
+use chrono::DateTime;
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::future::join_all;
+
+use crate::{
+    archive::{read_archive, Ingest, Route},
+    attributes::find_attributes,
+    catalog::Catalog,
+    config::Config,
+    currency::{extract_currency_to_usd, update_rates},
+    daemon,
+    fetch::FetchMode,
+    identify::{identify_watch, WatchIdError},
+    import::{import_csv, ImportOptions},
+    listing_kind::{classify_listing, ListingKind},
+    paths,
+    prelude::*,
+    registry,
+    tokenize::tokenize_watch_info,
+};
+
+#[derive(Parser)]
+#[command(about = "Collects watch listing prices from forums and marketplaces")]
+pub struct Cli {
+    /// Directory the price databases are stored in
+    #[arg(long, global = true, default_value = "data")]
+    pub data_dir: PathBuf,
+
+    /// Monthly USD conversion rates file
+    #[arg(long, global = true, default_value = "rates.json")]
+    pub rates: PathBuf,
+
+    /// Known references of each brand, identification works without it
+    #[arg(long, global = true, default_value = "catalog.toml")]
+    pub catalog: PathBuf,
+
+    /// Sources config, the built-in sources are used if it doesn't exist
+    #[arg(long, global = true, default_value = "scraper.toml")]
+    pub config: PathBuf,
+
+    /// Defaults to `scrape` when omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Update the price databases
+    Scrape {
+        /// Only run the scrapers with these names (repeatable)
+        #[arg(long)]
+        only: Vec<String>,
+
+        /// Keep running and refresh each scraper on its own schedule
+        #[arg(long)]
+        daemon: bool,
+
+        /// Save every fetched page to this directory
+        #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+        record: Option<PathBuf>,
+
+        /// Read pages from a directory written by --record instead of the network
+        #[arg(long, value_name = "DIR")]
+        replay: Option<PathBuf>,
+    },
+    /// List the stored price databases
+    ListDbs,
+    /// Write the entries of the stored price databases to a single file
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+
+        /// Output file, stdout when omitted
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Only export these databases (repeatable)
+        #[arg(long)]
+        db: Vec<String>,
+
+        /// Only export listings of these kinds (repeatable). Asking prices
+        /// by default, want to buy and trade posts have to be asked for
+        #[arg(
+            long,
+            value_enum,
+            default_values_t = [ListingKind::ForSale, ListingKind::ForSaleOrTrade],
+            conflicts_with = "all_kinds"
+        )]
+        kind: Vec<ListingKind>,
+
+        /// Also export listings stored before listings had a kind, they may
+        /// be want to buy or trade posts
+        #[arg(long)]
+        include_unclassified: bool,
+
+        /// Export listings of every kind, and those stored before listings
+        /// had one
+        #[arg(long, conflicts_with = "include_unclassified")]
+        all_kinds: bool,
+
+        /// Also export listings the title didn't identify surely enough,
+        /// they are marked `needs_review`
+        #[arg(long)]
+        include_review: bool,
+    },
+    /// Add auction results or other price observations from a CSV file
+    Import {
+        file: PathBuf,
+
+        /// Database to add the rows to
+        #[arg(long)]
+        db: String,
+
+        /// Header of a field's column when it isn't a common name, such as
+        /// `price=Hammer (EUR)`. Fields: date, title, brand, ref, price,
+        /// currency, sold, source (repeatable)
+        #[arg(long = "column", value_name = "FIELD=HEADER", value_parser = parse_column)]
+        columns: Vec<(String, String)>,
+
+        /// chrono format of the date column
+        #[arg(long)]
+        date_format: Option<String>,
+
+        /// Currency of prices without a currency column or symbol
+        #[arg(long)]
+        currency: Option<String>,
+    },
+    /// Backfill prices from Web Archive WARC files or browser HAR captures.
+    /// Every archived page goes to the source of the config it belongs to.
+    Ingest {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Only feed these sources (repeatable)
+        #[arg(long)]
+        only: Vec<String>,
+    },
+    /// Show how a listing title is identified
+    Identify { title: String },
+    /// Manage the conversion rates file
+    Rates {
+        #[command(subcommand)]
+        command: RatesCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RatesCommand {
+    /// Download the monthly conversion rates since 2000
+    Update,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl Cli {
+    pub async fn run(self) -> Result<()> {
+        paths::set_data_dir(self.data_dir)?;
+        paths::set_rates_path(self.rates)?;
+        paths::set_catalog_path(self.catalog)?;
+
+        match self.command.unwrap_or(Command::Scrape {
+            only: Vec::new(),
+            daemon: false,
+            record: None,
+            replay: None,
+        }) {
+            Command::Scrape {
+                only,
+                daemon,
+                record,
+                replay,
+            } => {
+                let config = Config::load(&self.config).await?;
+                let mode = match (record, replay) {
+                    (Some(dir), _) => FetchMode::Record(dir),
+                    (_, Some(dir)) => FetchMode::Replay(dir),
+                    _ => FetchMode::Live,
+                };
+
+                scrape(&config, mode, only, daemon).await
+            }
+            Command::ListDbs => list_dbs().await,
+            Command::Export {
+                format,
+                output,
+                db,
+                kind,
+                include_unclassified,
+                all_kinds,
+                include_review,
+            } => {
+                let filter = ExportFilter {
+                    kinds: (!all_kinds).then_some(kind),
+                    unclassified: include_unclassified || all_kinds,
+                    review: include_review,
+                };
+
+                export(format, output, db, &filter).await
+            }
+            Command::Import {
+                file,
+                db,
+                columns,
+                date_format,
+                currency,
+            } => {
+                let options = ImportOptions {
+                    columns,
+                    date_format,
+                    currency,
+                };
+
+                import(&file, db, &options).await
+            }
+            Command::Ingest { files, only } => {
+                let config = Config::load(&self.config).await?;
+
+                ingest(&config, &files, only).await
+            }
+            Command::Identify { title } => {
+                identify(&title);
+                Ok(())
+            }
+            Command::Rates {
+                command: RatesCommand::Update,
+            } => update_rates(paths::rates_path()).await,
+        }
+    }
+}
+
+async fn scrape(config: &Config, mode: FetchMode, only: Vec<String>, daemon: bool) -> Result<()> {
+    let mut scrapers = registry::build(config, mode)?;
+
+    if let Some(name) = only
+        .iter()
+        .find(|name| !scrapers.iter().any(|(s, _)| s.name() == name.as_str()))
+    {
+        return Err(WatchError::UnknownScraper(name.clone()));
+    }
+
+    if !only.is_empty() {
+        scrapers.retain(|(s, _)| only.iter().any(|name| name == s.name()));
+    }
+
+    if daemon {
+        daemon::run(scrapers).await;
+        return Ok(());
+    }
+
+    let mut scrapers: Vec<_> = scrapers.into_iter().map(|(s, _)| s).collect();
+    let results = join_all(scrapers.iter_mut().map(|s| s.update())).await;
+
+    for r in results {
+        if let Err(e) = r {
+            println!("{e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads every database in the data directory without knowing its entry type
+async fn read_dbs() -> Result<Vec<PriceDatabase<serde_json::Value>>> {
+    let mut dbs = Vec::new();
+    let mut dir = match tokio::fs::read_dir(paths::data_dir()).await {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(dbs),
+        Err(e) => return Err(e.into()),
+    };
+
+    while let Some(file) = dir.next_entry().await? {
+        let path = file.path();
+
+        if path.extension().map_or(true, |ext| ext != "json") {
+            continue;
+        }
+
+        let s = tokio::fs::read_to_string(&path).await?;
+
+        match serde_json::from_str(&s) {
+            Ok(db) => dbs.push(db),
+            Err(e) => eprintln!("{}: {e}", path.display()),
+        }
+    }
+
+    dbs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(dbs)
+}
+
+async fn list_dbs() -> Result<()> {
+    use std::sync::atomic::Ordering;
+
+    for db in read_dbs().await? {
+        let saved = DateTime::from_timestamp(db.timestamp.load(Ordering::Relaxed), 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+
+        println!(
+            "{:<24} {:>8} entries  saved {saved}  position {}",
+            db.name,
+            db.entries.len(),
+            db.position
+        );
+    }
+
+    Ok(())
+}
+
+/// Which stored entries are exported
+pub struct ExportFilter {
+    /// kinds of listings to export, None for all of them
+    pub kinds: Option<Vec<ListingKind>>,
+    /// export entries stored without a kind
+    pub unclassified: bool,
+    /// export entries that need a review
+    pub review: bool,
+}
+
+impl ExportFilter {
+    pub fn keeps(&self, entry: &serde_json::Value) -> bool {
+        let needs_review = entry.get("needs_review").and_then(|v| v.as_bool());
+
+        if needs_review == Some(true) && !self.review {
+            return false;
+        }
+
+        let kind = entry
+            .get("kind")
+            .and_then(|kind| serde_json::from_value::<ListingKind>(kind.clone()).ok());
+
+        match (kind, &self.kinds) {
+            (_, None) => true,
+            (None, _) => self.unclassified,
+            (Some(kind), Some(kinds)) => kinds.contains(&kind),
+        }
+    }
+}
+
+async fn export(
+    format: ExportFormat,
+    output: Option<PathBuf>,
+    only: Vec<String>,
+    filter: &ExportFilter,
+) -> Result<()> {
+    use serde_json::Value;
+
+    let mut rows = Vec::new();
+
+    for db in read_dbs().await? {
+        if !only.is_empty() && !only.contains(&db.name) {
+            continue;
+        }
+
+        for entry in db.entries {
+            if !filter.keeps(&entry) {
+                continue;
+            }
+
+            let mut row = serde_json::Map::new();
+
+            row.insert("source".to_owned(), Value::String(db.name.clone()));
+
+            if let Value::Object(fields) = entry {
+                row.extend(fields);
+            }
+
+            rows.push(row);
+        }
+    }
+
+    let data = match format {
+        ExportFormat::Json => serde_json::to_vec_pretty(&rows)?,
+        ExportFormat::Csv => {
+            // databases can have different entry types, use every field seen
+            let source = "source".to_owned();
+            let mut columns: Vec<&String> = vec![&source];
+
+            for key in rows.iter().flat_map(|row| row.keys()) {
+                if !columns.contains(&key) {
+                    columns.push(key);
+                }
+            }
+
+            let mut writer = csv::Writer::from_writer(Vec::new());
+
+            writer.write_record(&columns)?;
+
+            for row in &rows {
+                writer.write_record(columns.iter().map(|&c| match row.get(c) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(s)) => s.clone(),
+                    Some(v) => v.to_string(),
+                }))?;
+            }
+
+            writer.into_inner().map_err(|e| e.into_error())?
+        }
+    };
+
+    match output {
+        Some(path) => {
+            tokio::fs::write(&path, data).await?;
+            eprintln!("Exported {} entries to {}", rows.len(), path.display());
+        }
+        None => {
+            use std::io::Write;
+            std::io::stdout().write_all(&data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_column(s: &str) -> std::result::Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(field, header)| (field.trim().to_lowercase(), header.to_owned()))
+        .ok_or_else(|| format!("expected FIELD=HEADER, got {s:?}"))
+}
+
+async fn import(file: &Path, db: String, options: &ImportOptions) -> Result<()> {
+    let mut data = PriceDatabase::<PriceEntry>::new(db);
+
+    data.load().await?;
+
+    let report = import_csv(std::fs::File::open(file)?, options, &mut data)?;
+
+    for (line, e) in &report.errors {
+        eprintln!("{}:{line}: {e}", file.display());
+    }
+
+    println!(
+        "Imported {} rows into {}, {} already there, {} failed",
+        report.imported,
+        data.name,
+        report.duplicates,
+        report.errors.len()
+    );
+
+    data.save().await
+}
+
+async fn ingest(config: &Config, files: &[PathBuf], only: Vec<String>) -> Result<()> {
+    if let Some(name) = only
+        .iter()
+        .find(|name| !config.sources.iter().any(|s| s.database() == **name))
+    {
+        return Err(WatchError::UnknownScraper(name.clone()));
+    }
+
+    let mut sources = Vec::new();
+
+    for source in &config.sources {
+        let name = source.database();
+
+        if !only.is_empty() && !only.contains(&name) {
+            continue;
+        }
+
+        let mut data = PriceDatabase::<PriceEntry>::new(name);
+
+        data.load().await?;
+        sources.push((Route::new(&source.kind)?, data));
+    }
+
+    let mut ingests: Vec<_> = sources
+        .iter_mut()
+        .map(|(route, data)| Ingest::new(route, data))
+        .collect();
+
+    // records go to their sources as they are read, a broken file ends
+    // where it breaks
+    for file in files {
+        let records = match read_archive(file) {
+            Ok(records) => records,
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                continue;
+            }
+        };
+        let mut read = 0;
+
+        for record in records {
+            match record {
+                Ok(record) => {
+                    read += 1;
+                    ingests.iter_mut().for_each(|ingest| ingest.add(&record));
+                }
+                Err(e) => eprintln!("{}: {e}", file.display()),
+            }
+        }
+
+        println!("Read {read} records from {}", file.display());
+    }
+
+    let reports: Vec<_> = ingests.into_iter().map(Ingest::finish).collect();
+
+    for ((_, data), report) in sources.iter().zip(reports) {
+        if report.pages == 0 && report.errors.is_empty() {
+            continue;
+        }
+
+        for (url, e) in &report.errors {
+            eprintln!("{url}: {e}");
+        }
+
+        println!(
+            "{}: {} pages, {} entries added, {} threads not in any thread list, {} failed",
+            data.name,
+            report.pages,
+            report.added,
+            report.orphans,
+            report.errors.len()
+        );
+
+        data.save().await?;
+    }
+
+    Ok(())
+}
+
+fn identify(title: &str) {
+    let tokens = tokenize_watch_info(title);
+
+    println!("tokens:   {tokens:?}");
+
+    let identification = identify_watch(&tokens, Catalog::global());
+
+    if identification.brands.is_empty() {
+        println!("brand:    {}", WatchIdError::Brand);
+    }
+
+    for brand in &identification.brands {
+        println!("brand:    {} ({:.2})", brand.value, brand.confidence);
+    }
+
+    if identification.brand().is_some() && identification.references.is_empty() {
+        println!("model no: {}", WatchIdError::ModelNo);
+    }
+
+    for reference in &identification.references {
+        match reference.value.watch {
+            Some(watch) => println!(
+                "model no: {} ({:.2}, catalog {:?})",
+                reference.value.model_no, reference.confidence, watch.info
+            ),
+            None => println!(
+                "model no: {} ({:.2})",
+                reference.value.model_no, reference.confidence
+            ),
+        }
+    }
+
+    if let Some(collection) = identification.collection {
+        println!("collection: {collection}");
+    }
+
+    let attributes = find_attributes(&tokens);
+
+    if !attributes.is_empty() {
+        println!("attributes: {attributes:?}");
+    }
+
+    println!("kind:     {:?}", classify_listing(title));
+
+    match identification.needs_review() {
+        true => println!(
+            "confidence: {:.2}, needs review",
+            identification.confidence()
+        ),
+        false => println!("confidence: {:.2}", identification.confidence()),
+    }
+
+    match extract_currency_to_usd(Utc::now().timestamp(), title) {
+        Ok(price) => println!("price:    ${price}"),
+        Err(e) => println!("price:    {e}"),
+    }
+}
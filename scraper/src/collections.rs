@@ -0,0 +1,123 @@
+/// Model families of each brand. A family names its brand when the title
+/// doesn't. Brands are spelled as in `BRANDS`.
+pub const BRAND_COLLECTIONS: &[(&str, &[&str])] = &[
+    (
+        "A. Lange & Söhne",
+        &["Saxonia", "Zeitwerk", "Datograph", "Odysseus"],
+    ),
+    (
+        "Audemars Piguet",
+        &[
+            "Royal Oak",
+            "Royal Oak Offshore",
+            "Royal Oak Concept",
+            "Code 11.59",
+            "Millenary",
+            "Jules Audemars",
+        ],
+    ),
+    ("Blancpain", &["Fifty Fathoms", "Villeret", "Air Command"]),
+    ("Breguet", &["Classique", "Type XX"]),
+    (
+        "Breitling",
+        &["Navitimer", "Superocean", "Chronomat", "Avenger"],
+    ),
+    ("Bvlgari", &["Octo", "Serpenti"]),
+    (
+        "Cartier",
+        &["Santos", "Tank", "Ballon Bleu", "Panthere", "Pasha"],
+    ),
+    ("Chopard", &["Alpine Eagle", "Happy Sport", "Mille Miglia"]),
+    ("Citizen", &["Eco-Drive", "Promaster"]),
+    ("F. P. Journe", &["Chronometre Bleu", "Centigraphe"]),
+    ("Girard-Perregaux", &["Laureato"]),
+    ("H. Moser & Cie", &["Endeavour", "Streamliner"]),
+    (
+        "Hamilton",
+        &["Khaki", "Jazzmaster", "Ventura", "Intra-Matic"],
+    ),
+    ("Hublot", &["Big Bang", "Classic Fusion"]),
+    (
+        "IWC",
+        &[
+            "Portugieser",
+            "Portuguese",
+            "Big Pilot",
+            "Aquatimer",
+            "Ingenieur",
+            "Portofino",
+            "Da Vinci",
+        ],
+    ),
+    (
+        "Jaeger-LeCoultre",
+        &[
+            "Reverso",
+            "Master Control",
+            "Master Ultra Thin",
+            "Polaris",
+            "Memovox",
+        ],
+    ),
+    ("Longines", &["HydroConquest", "Legend Diver", "Conquest"]),
+    (
+        "Nomos Glashütte",
+        &["Tangente", "Orion", "Ludwig", "Tetra", "Ahoi"],
+    ),
+    (
+        "Omega",
+        &[
+            "Speedmaster",
+            "Seamaster",
+            "Constellation",
+            "De Ville",
+            "Aqua Terra",
+            "Planet Ocean",
+        ],
+    ),
+    ("Oris", &["Aquis", "Big Crown", "Divers Sixty-Five"]),
+    ("Panerai", &["Luminor", "Radiomir", "Submersible"]),
+    (
+        "Patek Philippe",
+        &[
+            "Nautilus",
+            "Aquanaut",
+            "Calatrava",
+            "Grand Complications",
+            "Gondolo",
+            "Golden Ellipse",
+        ],
+    ),
+    ("Piaget", &["Altiplano"]),
+    (
+        "Rolex",
+        &[
+            "Submariner",
+            "Daytona",
+            "GMT-Master",
+            "GMT-Master II",
+            "Datejust",
+            "Day-Date",
+            "Explorer",
+            "Explorer II",
+            "Sea-Dweller",
+            "Deepsea",
+            "Yacht-Master",
+            "Sky-Dweller",
+            "Milgauss",
+            "Air-King",
+            "Oyster Perpetual",
+            "Cellini",
+        ],
+    ),
+    ("Seiko", &["Prospex", "Presage", "Astron"]),
+    ("TAG Heuer", &["Carrera", "Monaco", "Aquaracer", "Autavia"]),
+    ("Tissot", &["PRX", "Seastar", "Le Locle"]),
+    ("TUDOR", &["Black Bay", "Pelagos", "Ranger", "North Flag"]),
+    ("Ulysse Nardin", &["Freak"]),
+    (
+        "Vacheron Constantin",
+        &["Overseas", "Patrimony", "Traditionnelle", "Historiques"],
+    ),
+    ("Zenith", &["El Primero", "Defy", "Chronomaster"]),
+];
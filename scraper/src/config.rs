@@ -0,0 +1,190 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{daemon::Schedule, http::Politeness, prelude::*, retry::RetryPolicy, session::Login};
+
+// used when no config file exists, so the binary works out of the box
+const DEFAULT_CONFIG: &str = include_str!("../../scraper.toml");
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub politeness: Politeness,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default, rename = "source")]
+    pub sources: Vec<SourceConfig>,
+}
+
+/// One `[[source]]` table of the config file
+#[derive(Deserialize)]
+pub struct SourceConfig {
+    #[serde(flatten)]
+    pub kind: SourceKind,
+    /// database name, derived from the kind when omitted
+    pub database: Option<String>,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    pub max_pages: Option<usize>,
+    /// detail pages fetched at the same time
+    pub concurrency: Option<usize>,
+    /// browse logged in with this account
+    pub login: Option<Login>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SourceKind {
+    RolexForums {
+        forum_id: usize,
+    },
+    /// any vBulletin forum, `[source.site]` describes its layout
+    Vbulletin {
+        forum_id: usize,
+        site: VBulletinConfig,
+    },
+    /// a XenForo forum, `forum` is its path segment like "sales-corner.12"
+    Xenforo {
+        forum: String,
+        site: XenForoConfig,
+    },
+    /// new posts of a subreddit, read from listing files in `dumps` (a file
+    /// or a directory) instead of reddit when given
+    Reddit {
+        subreddit: String,
+        dumps: Option<PathBuf>,
+        #[serde(default)]
+        site: RedditConfig,
+    },
+    /// schema.org product offers of dealer pages, and of the pages they link
+    /// to with `product_links` when given
+    ProductOffers {
+        urls: Vec<String>,
+        product_links: Option<String>,
+    },
+    /// any site with a list of threads, `[source.site]` has its url
+    /// templates and selectors
+    Selectors {
+        site: SelectorConfig,
+    },
+    /// an RSS or Atom feed of new threads or listings, read from `file`
+    /// instead of `url` when given
+    Feed {
+        url: String,
+        file: Option<PathBuf>,
+    },
+}
+
+impl SourceKind {
+    /// Site layout and forum id of sources scraped by `VBulletinForum`
+    pub fn vbulletin(&self) -> Option<(VBulletinConfig, usize)> {
+        match self {
+            SourceKind::RolexForums { forum_id } => {
+                Some((VBulletinConfig::rolex_forums(), *forum_id))
+            }
+            SourceKind::Vbulletin { forum_id, site } => Some((site.clone(), *forum_id)),
+            SourceKind::Xenforo { .. }
+            | SourceKind::Reddit { .. }
+            | SourceKind::ProductOffers { .. }
+            | SourceKind::Selectors { .. }
+            | SourceKind::Feed { .. } => None,
+        }
+    }
+}
+
+/// Daemon refresh policy, every value in seconds. Missing values use the
+/// defaults of `Schedule`.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    pub interval: Option<u64>,
+    pub jitter: Option<u64>,
+    pub backoff: Option<u64>,
+    pub max_backoff: Option<u64>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ScheduleConfig {
+    pub fn schedule(&self) -> Schedule {
+        let default = Schedule::default();
+        let secs = |value: Option<u64>, default: Duration| {
+            value.map(Duration::from_secs).unwrap_or(default)
+        };
+
+        Schedule {
+            interval: secs(self.interval, default.interval),
+            jitter: secs(self.jitter, default.jitter),
+            backoff: secs(self.backoff, default.backoff),
+            max_backoff: secs(self.max_backoff, default.max_backoff),
+        }
+    }
+}
+
+fn host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+impl SourceConfig {
+    pub fn database(&self) -> String {
+        match (&self.database, &self.kind) {
+            (Some(name), _) => name.clone(),
+            (None, SourceKind::RolexForums { forum_id }) => format!("RolexForums_{forum_id}"),
+            (None, SourceKind::Vbulletin { forum_id, site }) => {
+                format!("{}_{forum_id}", host(&site.base_url))
+            }
+            (None, SourceKind::Xenforo { forum, site }) => {
+                format!("{}_{forum}", host(&site.base_url))
+            }
+            (None, SourceKind::Reddit { subreddit, .. }) => format!("Reddit_{subreddit}"),
+            (None, SourceKind::ProductOffers { urls, .. }) => {
+                format!("Offers_{}", host(urls.first().map_or("", String::as_str)))
+            }
+            (None, SourceKind::Selectors { site }) => host(&site.list_url),
+            (None, SourceKind::Feed { url, .. }) => format!("Feed_{}", host(url)),
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(s: &str) -> Result<Self> {
+        let config: Self = toml::from_str(s)?;
+
+        config.politeness.validate()?;
+
+        // two sources writing the same database would overwrite each other
+        for (i, source) in config.sources.iter().enumerate() {
+            let name = source.database();
+
+            if config.sources[..i].iter().any(|s| s.database() == name) {
+                return Err(WatchError::Config(format!("duplicate database {name}")));
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Reads the config file at `path`, or the built-in config if it doesn't
+    /// exist
+    pub async fn load(path: &Path) -> Result<Self> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(s) => Self::parse(&s),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                println!("{} not found, using built-in sources", path.display());
+
+                Self::parse(DEFAULT_CONFIG)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
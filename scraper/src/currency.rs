@@ -1,6 +1,6 @@
-use std::str::FromStr;
+use std::{path::Path, str::FromStr, sync::OnceLock};
 
-use chrono::{Months, TimeZone};
+use chrono::{Months, NaiveDate, TimeZone};
 
 use crate::prelude::*;
 
@@ -67,50 +67,71 @@ pub fn extract_currency(s: &str) -> Result<(&(&str, char), String)> {
 }
 
 pub fn extract_currency_to_usd(timestamp: i64, s: &str) -> Result<u32> {
-    // load conversion rates from local store
-    lazy_static! {
-        static ref CONVERSION_RATES: HashMap<Box<str>, Box<[f64]>> =
-            serde_json::from_str(&std::fs::read_to_string("./rates.json").unwrap()).unwrap();
-    }
+    // extract currency parts (this is in any currency format. conversion to USD below.)
+    let (&(code, _), amount) = extract_currency(s)?;
 
-    // convert conversion rates store by timestamp
-    lazy_static! {
-        static ref CONVERSION_RATES_BY_TIMESTAMP: HashMap<Box<str>, Box<[(i64, f64)]>> =
-            CONVERSION_RATES
+    to_usd(timestamp, code, &amount)
+}
+
+/// Monthly rates of each currency with the time their month starts
+type Rates = HashMap<Box<str>, Box<[(i64, f64)]>>;
+
+fn read_rates() -> Result<Rates> {
+    let path = crate::paths::rates_path();
+    let rates: HashMap<Box<str>, Box<[f64]>> =
+        serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let start = Utc
+        .with_ymd_and_hms(RATES_START_YEAR, 1, 1, 0, 0, 0)
+        .unwrap();
+
+    Ok(rates
+        .into_iter()
+        .map(|(code, rates)| {
+            let rates = rates
                 .iter()
-                .flat_map(|(code, rates)| {
-                    let rates_with_timestamps = rates
-                        .iter()
-                        .enumerate()
-                        .flat_map(|(i_month, value)| {
-                            match Utc
-                                .with_ymd_and_hms(2000, 1, 1, 0, 0, 0)
-                                .unwrap()
-                                .checked_add_months(Months::new(i_month as u32))
-                                .and_then(|d| Some(d.timestamp()))
-                            {
-                                Some(t) => Some((t, *value)),
-                                None => None,
-                            }
-                        })
-                        .collect::<Vec<(i64, f64)>>()
-                        .into_boxed_slice();
-
-                    Some((code.clone(), rates_with_timestamps))
+                .enumerate()
+                .filter_map(|(i_month, value)| {
+                    start
+                        .checked_add_months(Months::new(i_month as u32))
+                        .map(|d| (d.timestamp(), *value))
                 })
-                .collect::<HashMap<Box<str>, Box<[(i64, f64)]>>>();
-    }
+                .collect();
 
-    // extract currency parts (this is in any currency format. conversion to USD below.)
-    let (&(code, symbol), amount) = extract_currency(s)?;
+            (code, rates)
+        })
+        .collect())
+}
 
-    // create currency from extracted information
-    let s = format!("{symbol}{amount}");
-    let mut currency = Currency::from_str(&s).or(Err(WatchIdError::Currency))?;
+/// The conversion rates file, read once. Without it no amount converts.
+fn conversion_rates() -> Result<&'static Rates> {
+    static RATES: OnceLock<Option<Rates>> = OnceLock::new();
+
+    RATES
+        .get_or_init(|| {
+            read_rates()
+                .map_err(|e| eprintln!("{}: {e}", crate::paths::rates_path().display()))
+                .ok()
+        })
+        .as_ref()
+        .ok_or_else(|| WatchIdError::ConversionRate.into())
+}
+
+/// Converts `amount` of the currency with ISO 4217 `code` to whole USD, at
+/// the rate of the month closest to `timestamp`. Any currency of the rates
+/// file converts, not only those with a symbol.
+pub fn to_usd(timestamp: i64, code: &str, amount: &str) -> Result<u32> {
+    let code = code.trim().to_ascii_lowercase();
+
+    if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_lowercase()) {
+        return Err(WatchIdError::Currency.into());
+    }
+
+    // the amount has no symbol, the converted one gets the dollar sign
+    let mut currency = Currency::from_str(amount).or(Err(WatchIdError::Currency))?;
 
     // get conversion rates list for the currency
-    let rates = CONVERSION_RATES_BY_TIMESTAMP
-        .get(code)
+    let rates = conversion_rates()?
+        .get(code.as_str())
         .ok_or(WatchIdError::ConversionRate)?;
 
     let mut best_dt = None;
@@ -129,9 +150,8 @@ pub fn extract_currency_to_usd(timestamp: i64, s: &str) -> Result<u32> {
         }
     }
 
-    // shouldn't fail. best_dt will always be Some since we fail the conversion if rates are not
-    // found for the currency
-    let (best_dt, best_conversion_rate) = best_dt.unwrap();
+    // a currency in the file may have no rates
+    let (_, best_conversion_rate) = best_dt.ok_or(WatchIdError::ConversionRate)?;
 
     // invert conversion rate X=USD*RATE -> USD=X/RATE -> USD=X*(1/RATE)
     let inverse_conversion_rate = 1.0 / best_conversion_rate;
@@ -142,3 +162,81 @@ pub fn extract_currency_to_usd(timestamp: i64, s: &str) -> Result<u32> {
     // convert decimal currency value to uint whole points
     Ok(currency.value().to_u32().ok_or(WatchIdError::Currency)?)
 }
+
+// https://www.xe.com/currencytables/
+const XE_RATES_URL: &str =
+    "https://www.xe.com/_next/data/lFsrCXsUT1R4egR02xO0Y/en/currencytables.json";
+
+// first month stored in the conversion rates file, index 0 of every rates list
+const RATES_START_YEAR: i32 = 2000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct XeResponse {
+    page_props: XePageProps,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct XePageProps {
+    historic_rates: Vec<XeRate>,
+}
+
+#[derive(Deserialize)]
+struct XeRate {
+    currency: String,
+    rate: f64,
+}
+
+/// Downloads monthly USD conversion rates from the start of 2000 until now and
+/// writes them to `path` in the format read by `extract_currency_to_usd`
+pub async fn update_rates(path: &Path) -> Result<()> {
+    let client = ClientBuilder::new().build()?;
+    let today = Utc::now().date_naive();
+
+    let mut rates: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut date = NaiveDate::from_ymd_opt(RATES_START_YEAR, 1, 1).unwrap();
+    let mut total_months = 0;
+
+    while date <= today {
+        println!("month: {}", date.format("%Y-%m"));
+
+        let response: XeResponse = client
+            .get(XE_RATES_URL)
+            .query(&[
+                ("from", "USD".to_owned()),
+                ("date", date.format("%Y-%m-%d").to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        for rate in response.page_props.historic_rates {
+            rates
+                .entry(rate.currency.to_lowercase())
+                .or_default()
+                .push(rate.rate);
+        }
+
+        total_months += 1;
+        date = date.checked_add_months(Months::new(1)).unwrap();
+    }
+
+    // rates are looked up by month index, so currencies with gaps can't be used
+    rates.retain(|code, r| {
+        if r.len() != total_months {
+            println!("removed {code}, didnt exist since year {RATES_START_YEAR}");
+            return false;
+        }
+
+        true
+    });
+
+    tokio::fs::write(path, serde_json::to_string(&rates)?).await?;
+
+    println!("Saved {} currencies to {}", rates.len(), path.display());
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use futures::future::join_all;
+use tokio::time::sleep;
+
+use crate::prelude::*;
+
+/// Refresh policy of a single scraper when running in daemon mode
+#[derive(Clone, Copy, Debug)]
+pub struct Schedule {
+    /// time to wait after a successful update
+    pub interval: Duration,
+    /// up to this much random delay is added to every wait so scrapers
+    /// sharing a host don't all wake up at once
+    pub jitter: Duration,
+    /// wait after the first failed update, doubled for every failure after it
+    pub backoff: Duration,
+    /// upper bound for the failure wait
+    pub max_backoff: Duration,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30 * 60),
+            jitter: Duration::from_secs(2 * 60),
+            backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl Schedule {
+    pub fn every(interval: Duration) -> Self {
+        Self {
+            interval,
+            ..Default::default()
+        }
+    }
+
+    /// Time to wait before the next update given the number of consecutive
+    /// failed updates so far
+    pub fn next_delay(&self, failures: u32) -> Duration {
+        let delay = match failures {
+            0 => self.interval,
+            n => self
+                .backoff
+                .saturating_mul(2u32.saturating_pow(n - 1))
+                .min(self.max_backoff),
+        };
+
+        delay + self.jitter.mul_f64(rand::random::<f64>())
+    }
+}
+
+/// Runs every scraper forever on its own schedule. Each scraper gets its own
+/// task, so a stalled or failing source never delays the others.
+pub async fn run(scrapers: Vec<(Box<dyn Scraper>, Schedule)>) {
+    let tasks = scrapers.into_iter().map(|(mut scraper, schedule)| {
+        tokio::spawn(async move {
+            let mut failures = 0;
+
+            loop {
+                match scraper.update().await {
+                    Ok(()) => failures = 0,
+                    Err(e) => {
+                        failures += 1;
+                        eprintln!(
+                            "{}: update failed ({failures} in a row): {e}",
+                            scraper.name()
+                        );
+                    }
+                }
+
+                let delay = schedule.next_delay(failures);
+
+                println!("{}: next update in {}s", scraper.name(), delay.as_secs());
+
+                sleep(delay).await;
+            }
+        })
+    });
+
+    for r in join_all(tasks).await {
+        if let Err(e) = r {
+            eprintln!("{e}");
+        }
+    }
+}
@@ -16,6 +16,52 @@ pub enum WatchError {
     Json(#[from] serde_json::Error),
     #[error(transparent)]
     WatchId(#[from] WatchIdError),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    #[error("Invalid config: {0}")]
+    Config(String),
+    #[error("HTTP {status} for {url}")]
+    HttpStatus {
+        url: String,
+        status: u16,
+        retry_after: Option<std::time::Duration>,
+    },
+    #[error("Invalid url {0}")]
+    InvalidUrl(String),
+    #[error("Disallowed by robots.txt: {0}")]
+    Disallowed(String),
+    #[error("Page layout changed, nothing matches {0}")]
+    MissingElement(String),
+    #[error("Failed to parse {field} from {value:?}")]
+    ParseField { field: &'static str, value: String },
+    #[error("Page not in the replay corpus: {0}")]
+    NotRecorded(String),
+    #[error("Login failed for {0}")]
+    LoginFailed(String),
+    #[error("Still not logged in after login: {0}")]
+    NotLoggedIn(String),
+    #[error("Cookie jar: {0}")]
+    Cookies(String),
+    #[error("No scraper named {0}")]
+    UnknownScraper(String),
+}
+
+impl WatchError {
+    /// The page was fetched but didn't look like we expected
+    pub fn is_parse(&self) -> bool {
+        matches!(
+            self,
+            WatchError::MissingElement(_)
+                | WatchError::ParseField { .. }
+                | WatchError::ParseTime
+                | WatchError::ParseError(_)
+                | WatchError::Xml(_)
+        )
+    }
 }
 
 pub type Result<T> = std::result::Result<T, WatchError>;
@@ -0,0 +1,120 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+
+use crate::{http::HttpClient, prelude::*};
+
+/// Source of page bodies for the scrapers. Swapping the implementation lets a
+/// whole scrape run against a recorded corpus instead of the live sites.
+pub trait Fetcher: Send + Sync {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>>;
+}
+
+/// Where scrapers get their pages from
+#[derive(Clone, Debug, Default)]
+pub enum FetchMode {
+    #[default]
+    Live,
+    /// fetch live and write every page to this directory
+    Record(PathBuf),
+    /// only read pages previously recorded to this directory
+    Replay(PathBuf),
+}
+
+impl FetchMode {
+    /// Wraps the fetcher that goes to the network according to the mode
+    pub fn fetcher(&self, live: Arc<dyn Fetcher>) -> Arc<dyn Fetcher> {
+        match self {
+            FetchMode::Live => live,
+            FetchMode::Record(dir) => Arc::new(RecordingFetcher::new(live, dir)),
+            FetchMode::Replay(dir) => Arc::new(ReplayFetcher::new(dir)),
+        }
+    }
+}
+
+impl Fetcher for HttpClient {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(self.get_text(url))
+    }
+}
+
+/// FNV-1a, stable across builds unlike `DefaultHasher`
+pub fn stable_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// File a page is stored under in a corpus directory. Readable enough to find
+/// a page by hand, the hash keeps urls that sanitize the same apart.
+pub fn corpus_file_name(url: &str) -> String {
+    let readable: String = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '_',
+        })
+        .take(120)
+        .collect();
+
+    format!("{readable}-{:016x}.html", stable_hash(url))
+}
+
+/// Fetches pages with another fetcher and saves a copy of each one
+pub struct RecordingFetcher {
+    inner: Arc<dyn Fetcher>,
+    dir: PathBuf,
+}
+
+impl RecordingFetcher {
+    pub fn new(inner: Arc<dyn Fetcher>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+}
+
+impl Fetcher for RecordingFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let body = self.inner.fetch(url).await?;
+
+            tokio::fs::create_dir_all(&self.dir).await?;
+            tokio::fs::write(self.dir.join(corpus_file_name(url)), &body).await?;
+
+            Ok(body)
+        })
+    }
+}
+
+/// Serves pages from a directory written by `RecordingFetcher`, never touches
+/// the network
+pub struct ReplayFetcher {
+    dir: PathBuf,
+}
+
+impl ReplayFetcher {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Fetcher for ReplayFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            match tokio::fs::read_to_string(self.dir.join(corpus_file_name(url))).await {
+                Ok(body) => Ok(body),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    Err(WatchError::NotRecorded(url.to_owned()))
+                }
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+}
@@ -0,0 +1,388 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::DateTime;
+use reqwest::{header::RETRY_AFTER, Client, RequestBuilder, Url};
+use reqwest_cookie_store::CookieStoreMutex;
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::prelude::*;
+
+/// How hard scrapers may hit a single host. Limits apply per host and are
+/// shared by every scraper using the same `HttpClient`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Politeness {
+    /// sustained request rate per host
+    pub requests_per_second: f64,
+    /// requests that may be made back to back after the host was idle
+    pub burst: u32,
+    /// minimum time between two requests to the same host, in milliseconds
+    pub min_delay_ms: u64,
+    /// skip urls disallowed by the host's robots.txt
+    pub robots_txt: bool,
+    pub user_agent: Option<String>,
+}
+
+impl Politeness {
+    /// Whether the settings describe a rate requests can be spaced by
+    pub fn validate(&self) -> Result<()> {
+        let rate = self.requests_per_second;
+
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(WatchError::Config(format!(
+                "requests_per_second must be a positive number, not {rate}"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Politeness {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 1.0,
+            burst: 3,
+            min_delay_ms: 500,
+            robots_txt: false,
+            user_agent: None,
+        }
+    }
+}
+
+/// Rules of a robots.txt that apply to us
+#[derive(Default)]
+pub(crate) struct Robots {
+    // (allow, path pattern)
+    rules: Vec<(bool, String)>,
+}
+
+impl Robots {
+    pub(crate) fn parse(s: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_lowercase();
+
+        let mut own = Vec::new();
+        let mut any = Vec::new();
+        // a group naming us may have no rules, "Disallow:" allows everything
+        let mut own_group = false;
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in s.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+
+            match key.as_str() {
+                "user-agent" => {
+                    // a user-agent line after rules starts a new group
+                    if in_rules {
+                        group_agents.clear();
+                        in_rules = false;
+                    }
+
+                    group_agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+
+                    let ours = group_agents
+                        .iter()
+                        .any(|a| a != "*" && user_agent.contains(a.as_str()));
+                    own_group |= ours;
+
+                    // an empty disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+
+                    let rule = (key == "allow", value.to_owned());
+
+                    if ours {
+                        own.push(rule.clone());
+                    }
+
+                    if group_agents.iter().any(|a| a == "*") {
+                        any.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // a group naming us replaces the catch-all group
+        Self {
+            rules: if own_group { own } else { any },
+        }
+    }
+
+    /// Whether a url may be fetched, `path` is its path and query
+    pub(crate) fn allowed(&self, path: &str) -> bool {
+        // the longest matching rule wins, allow wins ties
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| robots_match(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map_or(true, |(allow, _)| *allow)
+    }
+}
+
+// robots.txt patterns are prefixes with `*` wildcards and an optional `$` end
+// anchor
+fn robots_match(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut last = None;
+
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+
+        last = Some(part);
+    }
+
+    match (anchored, last) {
+        (false, _) => true,
+        (true, None) => rest.is_empty(),
+        (true, Some(last)) => path.ends_with(last),
+    }
+}
+
+/// Retry-After is either a number of seconds or an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+struct Host {
+    tokens: f64,
+    last_refill: Instant,
+    last_request: Option<Instant>,
+    robots: Option<Arc<Robots>>,
+}
+
+/// HTTP client shared by all scrapers that spaces out requests per host
+pub struct HttpClient {
+    client: Client,
+    politeness: Politeness,
+    // shared with the clients created by `with_cookies`
+    hosts: Arc<std::sync::Mutex<HashMap<String, Arc<Mutex<Host>>>>>,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(Politeness::default()).unwrap()
+    }
+}
+
+impl HttpClient {
+    fn builder(politeness: &Politeness) -> ClientBuilder {
+        let mut builder = ClientBuilder::new().tcp_nodelay(true);
+
+        if let Some(user_agent) = &politeness.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        builder
+    }
+
+    pub fn new(politeness: Politeness) -> Result<Self> {
+        politeness.validate()?;
+
+        Ok(Self {
+            client: Self::builder(&politeness).build()?,
+            politeness,
+            hosts: Default::default(),
+        })
+    }
+
+    /// A client with its own cookie jar that still shares the per-host limits
+    /// of this one
+    pub fn with_cookies(&self, cookies: Arc<CookieStoreMutex>) -> Result<Self> {
+        Ok(Self {
+            client: Self::builder(&self.politeness)
+                .cookie_provider(cookies)
+                .build()?,
+            politeness: self.politeness.clone(),
+            hosts: self.hosts.clone(),
+        })
+    }
+
+    /// A client sending `user_agent` that still shares the per-host limits
+    /// of this one
+    pub fn with_user_agent(&self, user_agent: &str) -> Result<Self> {
+        let politeness = Politeness {
+            user_agent: Some(user_agent.to_owned()),
+            ..self.politeness.clone()
+        };
+
+        Ok(Self {
+            client: Self::builder(&politeness).build()?,
+            politeness,
+            hosts: self.hosts.clone(),
+        })
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    fn host(&self, host: &str) -> Arc<Mutex<Host>> {
+        self.hosts
+            .lock()
+            .unwrap()
+            .entry(host.to_owned())
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(Host {
+                    tokens: self.politeness.burst.max(1) as f64,
+                    last_refill: Instant::now(),
+                    last_request: None,
+                    robots: None,
+                }))
+            })
+            .clone()
+    }
+
+    /// Waits until the host of `url` may be requested again and takes a token
+    /// from its bucket. Waiters on the same host are served in order.
+    pub async fn wait_turn(&self, url: &Url) {
+        let host = self.host(url.host_str().unwrap_or(""));
+        let mut host = host.lock().await;
+
+        let rate = self.politeness.requests_per_second;
+        let burst = self.politeness.burst.max(1) as f64;
+        let min_delay = Duration::from_millis(self.politeness.min_delay_ms);
+
+        loop {
+            let now = Instant::now();
+
+            host.tokens = (host.tokens + (now - host.last_refill).as_secs_f64() * rate).min(burst);
+            host.last_refill = now;
+
+            let spacing = host
+                .last_request
+                .map(|t| min_delay.saturating_sub(now - t))
+                .unwrap_or_default();
+            let refill = Duration::from_secs_f64(((1.0 - host.tokens) / rate).max(0.0));
+            let wait = spacing.max(refill);
+
+            if wait.is_zero() {
+                break;
+            }
+
+            sleep(wait).await;
+        }
+
+        host.tokens -= 1.0;
+        host.last_request = Some(Instant::now());
+    }
+
+    async fn robots(&self, url: &Url) -> Arc<Robots> {
+        let host = self.host(url.host_str().unwrap_or(""));
+
+        if let Some(robots) = &host.lock().await.robots {
+            return robots.clone();
+        }
+
+        let mut robots_url = url.clone();
+
+        robots_url.set_path("/robots.txt");
+        robots_url.set_query(None);
+
+        self.wait_turn(&robots_url).await;
+
+        // a missing or unreachable robots.txt allows everything
+        let user_agent = self.politeness.user_agent.as_deref().unwrap_or("");
+        let robots = match self.client.get(robots_url).send().await {
+            Ok(r) if r.status().is_success() => {
+                Robots::parse(&r.text().await.unwrap_or_default(), user_agent)
+            }
+            _ => Robots::default(),
+        };
+        let robots = Arc::new(robots);
+
+        host.lock().await.robots = Some(robots.clone());
+
+        robots
+    }
+
+    /// Sends the request built by `request` once the host's rate limit
+    /// allows it and returns the response body
+    async fn send(
+        &self,
+        url: &str,
+        request: impl FnOnce(&Client, Url) -> RequestBuilder,
+    ) -> Result<String> {
+        let parsed = Url::parse(url).map_err(|_| WatchError::InvalidUrl(url.to_owned()))?;
+
+        if self.politeness.robots_txt {
+            // rules like `Disallow: /search.php?do=` are about the query too
+            let path = match parsed.query() {
+                Some(query) => format!("{}?{query}", parsed.path()),
+                None => parsed.path().to_owned(),
+            };
+
+            if !self.robots(&parsed).await.allowed(&path) {
+                return Err(WatchError::Disallowed(url.to_owned()));
+            }
+        }
+
+        self.wait_turn(&parsed).await;
+
+        let response = request(&self.client, parsed).send().await?;
+        let status = response.status();
+
+        if !status.is_success() {
+            return Err(WatchError::HttpStatus {
+                url: url.to_owned(),
+                status: status.as_u16(),
+                retry_after: response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after),
+            });
+        }
+
+        Ok(response.text().await?)
+    }
+
+    /// Fetches `url` as text once the host's rate limit allows it
+    pub async fn get_text(&self, url: &str) -> Result<String> {
+        self.send(url, |client, url| client.get(url)).await
+    }
+
+    /// Posts a form to `url` once the host's rate limit allows it
+    pub async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<String> {
+        self.send(url, |client, url| client.post(url).form(form))
+            .await
+    }
+}
@@ -1,5 +1,10 @@
+use crate::brand_aliases::BRAND_ALIASES;
+use crate::brand_hierarchy::{canonical_brand, parent_brand, BRAND_STOP_WORDS};
 use crate::brand_tokens::*;
+use crate::catalog::{reference_key, Catalog, CatalogWatch};
+use crate::collections::BRAND_COLLECTIONS;
 use crate::prelude::*;
+use crate::tokenize::tokenize_watch_info;
 
 use thiserror::Error;
 
@@ -15,61 +20,98 @@ pub enum WatchIdError {
     ConversionRate,
 }
 
-const BAD_MODEL_NO_CHARS: [char; 2] = ['&', ')'];
+const BAD_MODEL_NO_CHARS: [char; 3] = ['&', ')', '$'];
 
-pub fn find_model_no(tokens: &Box<[Box<str>]>) -> Result<&Box<str>> {
-    let mut model_no = None;
-    let mut best_match_count = 0;
-    let mut best_char_match_count = 0;
+// a reference from the catalog surely is one, the token that looks most like
+// a reference probably is, the other tokens that look like one rarely are
+const CATALOG_REFERENCE_CONFIDENCE: f64 = 1.0;
+const LIKELY_REFERENCE_CONFIDENCE: f64 = 0.6;
+const OTHER_REFERENCE_CONFIDENCE: f64 = 0.3;
+// brand of a catalog reference in a title that doesn't name one
+const CATALOG_BRAND_CONFIDENCE: f64 = 0.9;
 
-    for t in tokens {
-        if t.ends_with("mm")
-            || t.ends_with("'s")
-            || (t.len() == 4 && t.ends_with('s'))
-            || BAD_MODEL_NO_CHARS.iter().any(|c| t.contains(*c))
+/// Listings identified less surely than this are kept for review
+pub const REVIEW_CONFIDENCE: f64 = 0.5;
+
+/// A brand or reference a listing may be about, and how sure that is from 0
+/// to 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<T> {
+    pub value: T,
+    pub confidence: f64,
+}
+
+/// A reference and its catalog entry, if the catalog has one
+#[derive(Debug, Clone)]
+pub struct Reference<'c> {
+    pub model_no: Box<str>,
+    pub watch: Option<&'c CatalogWatch>,
+}
+
+/// Length and number of leading or trailing letters of a token that looks
+/// like a reference: 4 digits in a row that aren't a year
+fn model_no_shape(t: &str) -> Option<(usize, usize)> {
+    if t.ends_with("mm")
+        || t.ends_with("'s")
+        || (t.len() == 4 && t.ends_with('s'))
+        || BAD_MODEL_NO_CHARS.iter().any(|c| t.contains(*c))
+    {
+        return None;
+    }
+
+    let t_no_symbols = t.replace(".", "0");
+
+    let token_len = t.len();
+    let num_subsequent_chars_end = t
+        .chars()
+        .rev()
+        .take_while(|&c| c.is_ascii_lowercase())
+        .count();
+    let num_subsequent_chars = t
+        .chars()
+        .take_while(|&c| c.is_ascii_lowercase())
+        .count()
+        .max(num_subsequent_chars_end);
+
+    let num_subsequent_digits = t_no_symbols
+        .chars()
+        .skip(
+            t_no_symbols
+                .chars()
+                .position(|x| x.is_numeric())
+                .unwrap_or(0),
+        )
+        .take_while(|&c| c.is_digit(10))
+        .count();
+
+    if num_subsequent_digits < 4 {
+        return None;
+    }
+
+    if let Ok(num) = t_no_symbols[..num_subsequent_digits].parse::<usize>() {
+        if num_subsequent_digits <= 4
+            && num >= MIN_YEAR
+            && num <= MAX_YEAR
+            && num_subsequent_chars == 0
         {
-            continue;
+            return None;
         }
+    }
 
-        let t_no_symbols = t.replace(".", "0");
+    Some((token_len, num_subsequent_chars))
+}
 
-        let token_len = t.len();
-        let num_subsequent_chars_end = t
-            .chars()
-            .rev()
-            .take_while(|&c| c.is_ascii_lowercase())
-            .count();
-        let num_subsequent_chars = t
-            .chars()
-            .take_while(|&c| c.is_ascii_lowercase())
-            .count()
-            .max(num_subsequent_chars_end);
-
-        let num_subsequent_digits = t_no_symbols
-            .chars()
-            .skip(
-                t_no_symbols
-                    .chars()
-                    .position(|x| x.is_numeric())
-                    .unwrap_or(0),
-            )
-            .take_while(|&c| c.is_digit(10))
-            .count();
+pub fn find_model_no(tokens: &[Box<str>]) -> Result<&Box<str>> {
+    let mut model_no = None;
+    let mut best_match_count = 0;
+    let mut best_char_match_count = 0;
 
-        if num_subsequent_digits >= 4
-            && token_len >= best_match_count
-            && num_subsequent_chars >= best_char_match_count
-        {
-            if let Ok(num) = t_no_symbols[..num_subsequent_digits].parse::<usize>() {
-                if num_subsequent_digits <= 4
-                    && num >= MIN_YEAR
-                    && num <= MAX_YEAR
-                    && num_subsequent_chars == 0
-                {
-                    continue;
-                }
-            }
+    for t in tokens {
+        let Some((token_len, num_subsequent_chars)) = model_no_shape(t) else {
+            continue;
+        };
 
+        if token_len >= best_match_count && num_subsequent_chars >= best_char_match_count {
             best_char_match_count = num_subsequent_chars;
             best_match_count = token_len;
 
@@ -83,31 +125,331 @@ pub fn find_model_no(tokens: &Box<[Box<str>]>) -> Result<&Box<str>> {
     }
 }
 
-pub fn find_brand(tokens: &Box<[Box<str>]>) -> Result<&'static str> {
-    let mut best_brand = None;
-    let mut best_match_percent = 0.0;
-    let mut best_match_count = 0;
-    let mut brand_match_percent = 0.0;
+/// References of a `brand` watch among `tokens`, most likely first. A
+/// reference from the catalog comes first, then the token that looks most
+/// like a reference and the other tokens that look like one.
+pub fn rank_references<'c>(
+    tokens: &[Box<str>],
+    brand: &str,
+    catalog: &'c Catalog,
+) -> Vec<Candidate<Reference<'c>>> {
+    let watch = catalog.find(brand, tokens);
+    let best = find_model_no(tokens).ok();
+    let mut candidates: Vec<_> = watch
+        .map(|watch| Candidate {
+            value: Reference {
+                model_no: watch.reference.to_lowercase().into(),
+                watch: Some(watch),
+            },
+            confidence: CATALOG_REFERENCE_CONFIDENCE,
+        })
+        .into_iter()
+        .collect();
+
+    let others = tokens
+        .iter()
+        .filter(|t| Some(*t) != best && model_no_shape(t).is_some());
+
+    for t in best.into_iter().chain(others) {
+        // the catalog reference, maybe without its bezel code
+        if watch.is_some_and(|w| reference_key(&w.reference).starts_with(&reference_key(t))) {
+            continue;
+        }
+
+        candidates.push(Candidate {
+            value: Reference {
+                model_no: t.clone(),
+                watch: None,
+            },
+            confidence: match Some(t) == best {
+                true => LIKELY_REFERENCE_CONFIDENCE,
+                false => OTHER_REFERENCE_CONFIDENCE,
+            },
+        });
+    }
+
+    candidates
+}
+
+/// Reference of a `brand` watch among `tokens`. A reference from the catalog
+/// wins over the token that looks most like one, and comes with what the
+/// catalog knows about it.
+pub fn find_reference<'c>(
+    tokens: &[Box<str>],
+    brand: &str,
+    catalog: &'c Catalog,
+) -> Result<(Box<str>, Option<&'c CatalogWatch>)> {
+    match rank_references(tokens, brand, catalog).into_iter().next() {
+        Some(Candidate { value, .. }) => Ok((value.model_no, value.watch)),
+        None => Err(WatchIdError::ModelNo.into()),
+    }
+}
+
+/// Words of tokens, "gmt-master" is "gmt" and "master"
+pub(crate) fn words<'a>(tokens: &'a [Box<str>]) -> Vec<&'a str> {
+    tokens
+        .iter()
+        .flat_map(|t| t.split(|c: char| !c.is_ascii_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Aliases and collections of a brand, and their words
+type BrandPhrases = Vec<(&'static str, Vec<(&'static str, Vec<String>)>)>;
+
+fn brand_phrases(table: &'static [(&'static str, &'static [&'static str])]) -> BrandPhrases {
+    table
+        .iter()
+        .map(|(brand, phrases)| {
+            let phrases = phrases
+                .iter()
+                .map(|phrase| {
+                    let words = words(&tokenize_watch_info(phrase))
+                        .into_iter()
+                        .map(str::to_owned)
+                        .collect();
+
+                    (*phrase, words)
+                })
+                .collect();
+
+            (*brand, phrases)
+        })
+        .collect()
+}
+
+lazy_static! {
+    static ref ALIASES: BrandPhrases = brand_phrases(BRAND_ALIASES);
+    static ref COLLECTIONS: BrandPhrases = brand_phrases(BRAND_COLLECTIONS);
+}
+
+/// The longest phrase of `brand` in `words` and its number of words
+fn phrase_match(
+    table: &BrandPhrases,
+    brand: &str,
+    words: &[&str],
+) -> Option<(&'static str, usize)> {
+    let (_, phrases) = table.iter().find(|(b, _)| *b == brand)?;
+
+    phrases
+        .iter()
+        .filter(|(_, phrase)| {
+            !phrase.is_empty() && words.windows(phrase.len()).any(|w| w == *phrase)
+        })
+        .map(|(name, phrase)| (*name, phrase.len()))
+        .max_by_key(|&(_, len)| len)
+}
+
+/// Model family of a `brand` watch named by `tokens`, "Royal Oak Offshore"
+/// rather than "Royal Oak" when the title has both words. Line names like
+/// "Oyster Perpetual" come last in the table, so "Oyster Perpetual
+/// Submariner" is a Submariner.
+pub fn find_collection(tokens: &[Box<str>], brand: &str) -> Option<&'static str> {
+    let (_, phrases) = COLLECTIONS.iter().find(|(b, _)| *b == brand)?;
+    let words = words(tokens);
+
+    // every match with where it starts and ends, in table order
+    let found: Vec<_> = phrases
+        .iter()
+        .filter(|(_, phrase)| !phrase.is_empty())
+        .flat_map(|(name, phrase)| {
+            words
+                .windows(phrase.len())
+                .enumerate()
+                .filter(move |(_, w)| w == phrase)
+                .map(move |(start, _)| (*name, start, start + phrase.len()))
+        })
+        .collect();
+
+    // a match inside a longer one is part of that name
+    found
+        .iter()
+        .find(|&&(_, start, end)| {
+            !found
+                .iter()
+                .any(|&(_, s, e)| s <= start && end <= e && e - s > end - start)
+        })
+        .map(|(name, ..)| *name)
+}
+
+// a collection names its brand less surely than the brand itself, but more
+// surely than one word of a longer brand name
+const COLLECTION_MATCH_PERCENT: f64 = 0.75;
+
+/// Brands named by `tokens`, most likely first. Names of one brand are one
+/// candidate, and a sub-brand hides its parent. The confidence is how much
+/// of the brand's name is there, halved for brands that match as well as
+/// another one.
+pub fn rank_brands(tokens: &[Box<str>]) -> Vec<Candidate<&'static str>> {
+    let title_words = words(tokens);
+    let mut matches = Vec::new();
 
     for (brand, brand_tokens) in BRAND_TOKENS {
-        let max_matches = brand_tokens.len();
-        let matches: Vec<_> = tokens
+        // "watch" or "co" are in too many titles to count
+        let brand_tokens: Vec<_> = brand_tokens
             .iter()
-            .filter(|t| brand_tokens.contains(&&***t))
+            .filter(|t| !BRAND_STOP_WORDS.contains(t))
             .collect();
-        let num_matches = matches.len();
-        let match_percent = (num_matches as f64 / max_matches as f64).min(1.0);
-
-        if num_matches >= best_match_count && match_percent > best_match_percent {
-            best_match_percent = match_percent;
-            best_match_count = num_matches;
-            best_brand = Some(brand);
-            brand_match_percent = match_percent;
+        let max_matches = brand_tokens.len().max(1);
+        let mut num_matches = tokens
+            .iter()
+            .filter(|t| brand_tokens.contains(&&&***t))
+            .count();
+        let mut match_percent = (num_matches as f64 / max_matches as f64).min(1.0);
+
+        // an alias names the whole brand
+        if let Some((_, alias_len)) = phrase_match(&ALIASES, brand, &title_words) {
+            match_percent = 1.0;
+            num_matches = num_matches.max(alias_len);
+        } else if match_percent < COLLECTION_MATCH_PERCENT
+            && phrase_match(&COLLECTIONS, brand, &title_words).is_some()
+        {
+            match_percent = COLLECTION_MATCH_PERCENT;
+            num_matches = num_matches.max(1);
+        }
+
+        if num_matches > 0 {
+            matches.push((canonical_brand(brand), match_percent, num_matches));
         }
     }
 
-    match best_brand {
-        Some(x) => Ok(x),
-        _ => Err(WatchIdError::ModelNo.into()),
+    // the stable sort keeps the order of BRANDS between equal matches
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.2.cmp(&a.2)));
+
+    // the best match of each brand
+    let mut brands: Vec<(&str, f64, usize)> = Vec::new();
+
+    for m in matches {
+        if !brands.iter().any(|(brand, ..)| *brand == m.0) {
+            brands.push(m);
+        }
+    }
+
+    // "grand seiko" names seiko too, and "seiko" is half of grand seiko.
+    // Of a sub-brand and its parent only the better match is kept.
+    let hidden: Vec<_> = brands
+        .iter()
+        .filter_map(|&(brand, percent, _)| {
+            let parent = parent_brand(brand)?;
+            let &(_, parent_percent, _) = brands.iter().find(|(b, ..)| *b == parent)?;
+
+            match parent_percent <= percent {
+                true => Some(parent),
+                false => Some(brand),
+            }
+        })
+        .collect();
+
+    brands.retain(|(brand, ..)| !hidden.contains(brand));
+
+    let best = brands.first().map(|&(_, percent, count)| (percent, count));
+    let tied = brands
+        .iter()
+        .filter(|&&(_, percent, count)| Some((percent, count)) == best)
+        .count();
+
+    brands
+        .into_iter()
+        .map(|(brand, percent, count)| Candidate {
+            value: brand,
+            confidence: match tied > 1 && Some((percent, count)) == best {
+                true => percent / 2.0,
+                false => percent,
+            },
+        })
+        .collect()
+}
+
+pub fn find_brand(tokens: &[Box<str>]) -> Result<&'static str> {
+    match rank_brands(tokens).first() {
+        Some(x) => Ok(x.value),
+        _ => Err(WatchIdError::Brand.into()),
+    }
+}
+
+/// What a listing is about: the brands it may name, and the references and
+/// model family of the most likely one
+#[derive(Debug, Default)]
+pub struct Identification<'c> {
+    pub brands: Vec<Candidate<&'c str>>,
+    pub references: Vec<Candidate<Reference<'c>>>,
+    /// from the catalog entry of the reference, or else the title
+    pub collection: Option<&'c str>,
+}
+
+impl<'c> Identification<'c> {
+    pub fn brand(&self) -> Option<&'c str> {
+        self.brands.first().map(|b| b.value)
+    }
+
+    pub fn reference(&self) -> Option<&Reference<'c>> {
+        self.references.first().map(|r| &r.value)
+    }
+
+    /// How sure the best brand and reference are together, 0 when either is
+    /// missing
+    pub fn confidence(&self) -> f64 {
+        match (self.brands.first(), self.references.first()) {
+            (Some(brand), Some(reference)) => brand.confidence * reference.confidence,
+            _ => 0.0,
+        }
+    }
+
+    pub fn needs_review(&self) -> bool {
+        self.confidence() < REVIEW_CONFIDENCE
+    }
+}
+
+/// Brand, reference and collection candidates of a listing title's tokens
+pub fn identify_watch<'c>(tokens: &[Box<str>], catalog: &'c Catalog) -> Identification<'c> {
+    identify_fields(tokens, None, &[], catalog)
+}
+
+/// Like `identify_watch`, for listings that also give the brand or reference
+/// in fields of their own: a CSV column, or the mpn and sku of a product.
+/// A field wins over the title when it names a brand or reference, `codes`
+/// are tried in order.
+pub fn identify_fields<'c>(
+    tokens: &[Box<str>],
+    brand: Option<&str>,
+    codes: &[&str],
+    catalog: &'c Catalog,
+) -> Identification<'c> {
+    let code_tokens: Vec<_> = codes.iter().map(|code| tokenize_watch_info(code)).collect();
+    let mut brands: Vec<Candidate<&'c str>> = brand
+        .map(|brand| rank_brands(&tokenize_watch_info(brand)))
+        .filter(|brands| !brands.is_empty())
+        .unwrap_or_else(|| rank_brands(tokens));
+
+    // a catalog reference names the brand of titles without one
+    if brands.is_empty() {
+        let brand = code_tokens
+            .iter()
+            .find_map(|code| catalog.brand_of(code))
+            .or_else(|| catalog.brand_of(tokens));
+
+        brands.extend(brand.map(|brand| Candidate {
+            value: brand,
+            confidence: CATALOG_BRAND_CONFIDENCE,
+        }));
+    }
+
+    let Some(brand) = brands.first().map(|b| b.value) else {
+        return Identification::default();
+    };
+    let references = code_tokens
+        .iter()
+        .map(|code| rank_references(code, brand, catalog))
+        .find(|references| !references.is_empty())
+        .unwrap_or_else(|| rank_references(tokens, brand, catalog));
+    let collection = references
+        .first()
+        .and_then(|r| r.value.watch?.info.collection.as_deref())
+        .or_else(|| find_collection(tokens, brand));
+
+    Identification {
+        brands,
+        references,
+        collection,
     }
 }
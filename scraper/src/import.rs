@@ -0,0 +1,326 @@
+use std::io::Read;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::{
+    catalog::Catalog,
+    currency::{extract_currency_to_usd, to_usd},
+    fetch::stable_hash,
+    identify::identify_fields,
+    prelude::*,
+    tokenize::tokenize_watch_info,
+};
+
+/// Fields a CSV row can hold, with the header names recognized for each one
+const COLUMN_ALIASES: &[(&str, &[&str])] = &[
+    ("date", &["date", "sale date", "sold date", "auction date"]),
+    (
+        "title",
+        &["title", "lot", "lot title", "description", "name"],
+    ),
+    ("brand", &["brand", "make", "maker", "manufacturer"]),
+    (
+        "ref",
+        &[
+            "ref",
+            "reference",
+            "ref no",
+            "model no",
+            "model_no",
+            "model",
+        ],
+    ),
+    (
+        "price",
+        &["price", "hammer", "hammer price", "sold price", "amount"],
+    ),
+    ("currency", &["currency"]),
+    ("sold", &["sold", "is_sold", "status"]),
+    (
+        "source",
+        &["source", "auction house", "house", "seller", "venue"],
+    ),
+];
+
+// tried in order when no date format is given
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%d %B %Y", "%B %d, %Y", "%d %b %Y", "%b %d, %Y"];
+const DATE_TIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// How to read a CSV file. Columns are found by their header, fields without
+/// an explicit header fall back to the common names in `COLUMN_ALIASES`.
+#[derive(Clone, Debug, Default)]
+pub struct ImportOptions {
+    /// field name to header, `price` to "Hammer (EUR)"
+    pub columns: Vec<(String, String)>,
+    /// chrono format of the date column, common formats and unix timestamps
+    /// are tried when omitted
+    pub date_format: Option<String>,
+    /// currency of prices without a currency column or symbol
+    pub currency: Option<String>,
+}
+
+/// Outcome of an import. Rows that fail don't stop the others.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// rows imported before, found by their content
+    pub duplicates: usize,
+    /// line number and what went wrong
+    pub errors: Vec<(usize, WatchError)>,
+}
+
+/// Column index of every field present in the header
+struct Columns(HashMap<&'static str, usize>);
+
+impl Columns {
+    fn new(headers: &csv::StringRecord, options: &ImportOptions) -> Result<Self> {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+        };
+        let mut columns = HashMap::new();
+
+        for (field, aliases) in COLUMN_ALIASES {
+            let explicit = options.columns.iter().find(|(f, _)| f == field);
+
+            let index = match explicit {
+                Some((_, header)) => Some(find(header).ok_or_else(|| {
+                    WatchError::Config(format!("no column {header:?} for {field}"))
+                })?),
+                None => aliases.iter().find_map(|alias| find(alias)),
+            };
+
+            if let Some(index) = index {
+                columns.insert(*field, index);
+            }
+        }
+
+        if let Some((field, _)) = options
+            .columns
+            .iter()
+            .find(|(f, _)| !COLUMN_ALIASES.iter().any(|(name, _)| name == f))
+        {
+            return Err(WatchError::Config(format!("unknown import field {field}")));
+        }
+
+        for field in ["date", "price"] {
+            if !columns.contains_key(field) {
+                return Err(WatchError::Config(format!("no {field} column")));
+            }
+        }
+
+        if !columns.contains_key("title") && !columns.contains_key("ref") {
+            return Err(WatchError::Config("no title or ref column".to_owned()));
+        }
+
+        Ok(Self(columns))
+    }
+
+    /// Trimmed, non-empty value of a field
+    fn get<'a>(&self, record: &'a csv::StringRecord, field: &str) -> Option<&'a str> {
+        self.0
+            .get(field)
+            .and_then(|&i| record.get(i))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    }
+}
+
+fn parse_date(value: &str, format: Option<&str>) -> Result<i64> {
+    let datetime = match format {
+        Some(format) => NaiveDateTime::parse_from_str(value, format)
+            .or_else(|_| NaiveDate::parse_from_str(value, format).map(|d| d.into()))?,
+        None => {
+            // unix seconds have 9 or 10 digits since 1973, a bare "2023" is
+            // a year
+            if (9..=10).contains(&value.len()) {
+                if let Ok(timestamp) = value.parse::<i64>() {
+                    return Ok(timestamp);
+                }
+            }
+
+            DATE_TIME_FORMATS
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+                .or_else(|| {
+                    DATE_FORMATS
+                        .iter()
+                        .find_map(|f| NaiveDate::parse_from_str(value, f).ok())
+                        .map(NaiveDate::into)
+                })
+                .ok_or_else(|| WatchError::ParseField {
+                    field: "date",
+                    value: value.to_owned(),
+                })?
+        }
+    };
+
+    Ok(datetime.and_utc().timestamp())
+}
+
+/// Amount of a price as digits and a decimal point, "6.100,50" and
+/// "6,100.50" are both "6100.50". A lone separator followed by three digits
+/// groups thousands, prices don't have three decimals.
+fn parse_amount(value: &str) -> Result<String> {
+    let invalid = || WatchError::ParseField {
+        field: "price",
+        value: value.to_owned(),
+    };
+    let number: String = value
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .collect();
+
+    let decimal = match (number.rfind('.'), number.rfind(',')) {
+        (Some(dot), Some(comma)) => Some(dot.max(comma)),
+        (Some(i), None) | (None, Some(i)) => {
+            let separator = number.as_bytes()[i] as char;
+            let once = number.matches(separator).count() == 1;
+
+            (once && number.len() - i - 1 != 3).then_some(i)
+        }
+        (None, None) => None,
+    };
+
+    let (whole, fraction) = match decimal {
+        Some(i) => (&number[..i], &number[i + 1..]),
+        None => (number.as_str(), ""),
+    };
+
+    // every group after the first has three digits
+    let mut groups = whole.split(['.', ',']);
+    let first = groups.next().unwrap_or_default();
+
+    if first.is_empty()
+        || groups.any(|g| g.len() != 3)
+        || fraction.contains(['.', ','])
+        || fraction.len() > 2
+    {
+        return Err(invalid());
+    }
+
+    let whole: String = whole.chars().filter(char::is_ascii_digit).collect();
+
+    Ok(match fraction.is_empty() {
+        true => whole,
+        false => format!("{whole}.{fraction}"),
+    })
+}
+
+fn parse_sold(value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "y" | "yes" | "true" | "x" | "sold" => Ok(true),
+        "0" | "n" | "no" | "false" | "unsold" | "passed" | "bought in" => Ok(false),
+        _ => Err(WatchError::ParseField {
+            field: "sold",
+            value: value.to_owned(),
+        }),
+    }
+}
+
+fn parse_row(
+    columns: &Columns,
+    record: &csv::StringRecord,
+    options: &ImportOptions,
+) -> Result<PriceEntry> {
+    let title = columns.get(record, "title").unwrap_or_default();
+    let reference = columns.get(record, "ref");
+    let identification = identify_fields(
+        &tokenize_watch_info(title),
+        columns.get(record, "brand"),
+        reference.as_slice(),
+        Catalog::global(),
+    );
+
+    if identification.brand().is_none() {
+        return Err(WatchIdError::Brand.into());
+    }
+
+    let date = columns.get(record, "date").ok_or(WatchError::ParseField {
+        field: "date",
+        value: String::new(),
+    })?;
+    let timestamp = parse_date(date, options.date_format.as_deref())?;
+
+    let price = columns.get(record, "price").ok_or(WatchError::ParseField {
+        field: "price",
+        value: String::new(),
+    })?;
+    // "EUR 6,100" carries its own currency
+    let code: String = price.chars().filter(char::is_ascii_alphabetic).collect();
+    let currency = columns
+        .get(record, "currency")
+        .or(Some(code.as_str()).filter(|c| c.len() == 3))
+        .or(options.currency.as_deref());
+    let usd = match currency {
+        Some(currency) => to_usd(timestamp, currency, &parse_amount(price)?)?,
+        // a currency symbol in the price
+        None => extract_currency_to_usd(timestamp, price)?,
+    };
+
+    // auction results and private sales are completed sales
+    let is_sold = columns
+        .get(record, "sold")
+        .map(parse_sold)
+        .transpose()?
+        .unwrap_or(true);
+
+    let origin = columns.get(record, "source");
+
+    // importing the same file twice must not duplicate rows, even after the
+    // rates or the identification changed, so only the raw fields count
+    let id = stable_hash(&format!(
+        "{date}|{title}|{}|{price}|{}|{}",
+        reference.unwrap_or_default(),
+        currency.unwrap_or_default(),
+        origin.unwrap_or_default()
+    ));
+
+    let mut entry =
+        PriceEntry::identified(id, timestamp, title, &identification).ok_or(WatchIdError::Brand)?;
+
+    entry.price = Some(usd);
+    entry.is_sold = is_sold;
+    entry.origin = origin.map(Into::into);
+
+    Ok(entry)
+}
+
+/// Adds the rows of a CSV file to a database
+pub fn import_csv(
+    reader: impl Read,
+    options: &ImportOptions,
+    data: &mut PriceDatabase<PriceEntry>,
+) -> Result<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let columns = Columns::new(reader.headers()?, options)?;
+    let mut report = ImportReport::default();
+
+    for (i, record) in reader.records().enumerate() {
+        // the header is line 1
+        let line = record
+            .as_ref()
+            .ok()
+            .and_then(|r| r.position())
+            .map_or(i + 2, |p| p.line() as usize);
+
+        match record
+            .map_err(WatchError::from)
+            .and_then(|record| parse_row(&columns, &record, options))
+        {
+            Ok(entry) if data.entries.iter().any(|e| e.id == entry.id) => {
+                report.duplicates += 1;
+            }
+            Ok(entry) => {
+                data.entries.push(entry);
+                report.imported += 1;
+            }
+            Err(e) => report.errors.push((line, e)),
+        }
+    }
+
+    data.entries.sort_by_key(|x| x.timestamp);
+
+    Ok(report)
+}
@@ -0,0 +1,152 @@
+use crate::prelude::*;
+
+/// What the poster of a listing wants
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum ListingKind {
+    /// FS or WTS, and listings that don't say
+    #[default]
+    ForSale,
+    /// FSOT, for sale or trade
+    ForSaleOrTrade,
+    /// WTB, the price is what the poster would pay
+    WantToBuy,
+    /// WTT, trade only
+    WantToTrade,
+    /// price checks, questions and other posts that aren't an offer
+    Other,
+}
+
+// the markers of each kind, titles put them in brackets, before a colon or
+// anywhere in the title
+const MARKERS: &[(ListingKind, &[&str])] = &[
+    (
+        ListingKind::ForSaleOrTrade,
+        &[
+            "fsot",
+            "fs/t",
+            "fs/ft",
+            "fs/tr",
+            "fs/trade",
+            "fs or trade",
+            "for sale or trade",
+        ],
+    ),
+    (ListingKind::ForSale, &["fs", "wts", "for sale"]),
+    (
+        ListingKind::WantToBuy,
+        &["wtb", "want to buy", "looking for"],
+    ),
+    (
+        ListingKind::WantToTrade,
+        &["wtt", "for trade", "or trade", "trade only"],
+    ),
+    (
+        ListingKind::Other,
+        &["pc", "price check", "valuation", "wdyt"],
+    ),
+];
+
+// markers that are ordinary words too, "well worth it" or "the most wanted
+// Daytona", they only count as a tag in brackets or at the start
+const TAG_MARKERS: &[(ListingKind, &[&str])] = &[
+    (ListingKind::WantToBuy, &["wanted", "lf"]),
+    (ListingKind::WantToTrade, &["ft"]),
+    (ListingKind::Other, &["worth", "question", "meta"]),
+];
+
+impl ListingKind {
+    /// Whether the price is an asking price
+    pub fn is_sale(self) -> bool {
+        matches!(self, ListingKind::ForSale | ListingKind::ForSaleOrTrade)
+    }
+}
+
+/// Words of a title, a word with '/' adds its parts too, "wtb/wtt" has both
+/// and "fs/t" is one marker
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+
+    for word in text
+        .split(|c: char| c.is_whitespace() || "[](){}:;,|!?-".contains(c))
+        .filter(|w| !w.is_empty())
+    {
+        words.push(word);
+
+        if word.contains('/') {
+            words.extend(word.split('/').filter(|w| !w.is_empty()));
+        }
+    }
+
+    words
+}
+
+/// Words of the tags of a title: what is in brackets, before a colon near
+/// the start, or the first word
+fn tag_words(title: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut rest = title;
+
+    while let Some(start) = rest.find(['[', '(', '{']) {
+        let Some(end) = rest[start..].find([']', ')', '}']) else {
+            break;
+        };
+
+        tags.extend(words(&rest[start + 1..start + end]));
+        rest = &rest[start + end + 1..];
+    }
+
+    let untagged = title.trim_start();
+    let lead = match untagged.split_once(':') {
+        Some((lead, _)) if words(lead).len() <= 3 => lead,
+        _ => untagged.split_whitespace().next().unwrap_or_default(),
+    };
+
+    tags.extend(words(lead));
+    tags
+}
+
+/// Kind of a listing from the markers in its title. "FS/WTT" offers a sale
+/// and a trade, a sale marker wins over any other and titles without one are
+/// for sale.
+pub fn classify_listing(title: &str) -> ListingKind {
+    let lower = title.to_lowercase();
+    let words = words(&lower);
+    let tags = tag_words(&lower);
+
+    let has_marker = |words: &[&str], markers: &[&str]| {
+        markers.iter().any(|marker| {
+            let marker: Vec<_> = marker.split(' ').collect();
+
+            words.windows(marker.len()).any(|w| w == marker.as_slice())
+        })
+    };
+    let found: Vec<_> = MARKERS
+        .iter()
+        .filter(|(_, markers)| has_marker(&words, markers))
+        .chain(
+            TAG_MARKERS
+                .iter()
+                .filter(|(_, markers)| has_marker(&tags, markers)),
+        )
+        .map(|(kind, _)| *kind)
+        .collect();
+    let has = |kind| found.contains(&kind);
+
+    if has(ListingKind::ForSaleOrTrade)
+        || has(ListingKind::ForSale) && has(ListingKind::WantToTrade)
+    {
+        ListingKind::ForSaleOrTrade
+    } else if has(ListingKind::ForSale) {
+        ListingKind::ForSale
+    } else if has(ListingKind::WantToBuy) {
+        ListingKind::WantToBuy
+    } else if has(ListingKind::WantToTrade) {
+        ListingKind::WantToTrade
+    } else if has(ListingKind::Other) {
+        ListingKind::Other
+    } else {
+        ListingKind::ForSale
+    }
+}
@@ -1,17 +1,32 @@
 #![allow(warnings)]
 
-use futures::future::join_all;
-use std::time::Duration;
-use tokio::time::sleep;
+use clap::Parser;
 
+mod archive;
+mod attributes;
 mod beep;
+mod brand_aliases;
+mod brand_hierarchy;
 mod brand_tokens;
 mod brands;
+mod catalog;
+mod cli;
+mod collections;
+mod config;
 mod currency;
+mod daemon;
 mod error;
+mod fetch;
+mod http;
 mod identify;
+mod import;
+mod listing_kind;
+mod paths;
 mod prelude;
+mod registry;
+mod retry;
 mod scrapers;
+mod session;
 mod tokenize;
 
 #[cfg(test)]
@@ -19,30 +34,8 @@ mod tests;
 
 #[tokio::main]
 async fn main() {
-    use prelude::*;
-
-    // lazy_static! {
-    //     static ref CONVERSION_RATES: HashMap<Box<str>, Box<[f64]>> =
-    //         serde_json::from_str(&std::fs::read_to_string("./rates.json").unwrap()).unwrap();
-    // }
-    //
-    // println!("{:?}", *CONVERSION_RATES);
-
-    let mut scrapers: Vec<Box<dyn Scraper>> = vec![
-        Box::new(OtherForum::default()),
-        Box::new(RolexForums::new(ROLEX_FORUMS_ID_ROLEX_ONLY)),
-        Box::new(RolexForums::new(ROLEX_FORUMS_ID_NON_ROLEX)),
-    ];
-
-    //loop {
-    let results = join_all(scrapers.iter_mut().map(|s| s.update())).await;
-
-    for r in results {
-        if let Err(e) = r {
-            println!("{e:?}");
-        }
+    if let Err(e) = cli::Cli::parse().run().await {
+        eprintln!("{e}");
+        std::process::exit(1);
     }
-
-    //sleep(Duration::from_secs(1)).await;
-    //}
 }
@@ -0,0 +1,80 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::prelude::*;
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+static RATES_PATH: OnceLock<PathBuf> = OnceLock::new();
+static CATALOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+// a path is set once, before its first use, and later uses all see the same
+fn set(cell: &OnceLock<PathBuf>, what: &str, path: PathBuf) -> Result<()> {
+    let set = cell.get_or_init(|| path.clone());
+
+    if *set != path {
+        return Err(WatchError::Config(format!(
+            "{what} is already {}, can't change it to {}",
+            set.display(),
+            path.display()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Sets the directory databases are stored in by default. This must happen
+/// before any database is loaded or saved, setting another one is an error.
+pub fn set_data_dir(path: impl Into<PathBuf>) -> Result<()> {
+    set(&DATA_DIR, "data dir", path.into())
+}
+
+/// Sets the conversion rates file. This must happen before any currency
+/// conversion, setting another one is an error.
+pub fn set_rates_path(path: impl Into<PathBuf>) -> Result<()> {
+    set(&RATES_PATH, "rates file", path.into())
+}
+
+/// Sets the reference catalog file. This must happen before anything is
+/// identified, setting another one is an error.
+pub fn set_catalog_path(path: impl Into<PathBuf>) -> Result<()> {
+    set(&CATALOG_PATH, "catalog file", path.into())
+}
+
+pub fn data_dir() -> &'static Path {
+    DATA_DIR.get_or_init(|| PathBuf::from("data"))
+}
+
+// tests run in the package dir and all of them read the repo's rates, no
+// matter which one converts a price first
+#[cfg(not(test))]
+const DEFAULT_RATES_PATH: &str = "rates.json";
+#[cfg(test)]
+const DEFAULT_RATES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../rates.json");
+
+pub fn rates_path() -> &'static Path {
+    RATES_PATH.get_or_init(|| PathBuf::from(DEFAULT_RATES_PATH))
+}
+
+pub fn catalog_path() -> &'static Path {
+    CATALOG_PATH.get_or_init(|| PathBuf::from("catalog.toml"))
+}
+
+pub fn db_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.json"))
+}
+
+pub fn db_backup_path(dir: &Path, name: &str, generation: usize) -> PathBuf {
+    dir.join(format!("{name}.json.{generation}"))
+}
+
+/// Pages that failed to parse are kept here
+pub fn diagnostics_dir() -> PathBuf {
+    data_dir().join("diagnostics")
+}
+
+/// Cookie jar of a logged in source
+pub fn cookies_path(name: &str) -> PathBuf {
+    data_dir().join("cookies").join(format!("{name}.json"))
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use crate::{
+    config::{Config, SourceConfig, SourceKind},
+    daemon::Schedule,
+    fetch::{FetchMode, Fetcher},
+    http::HttpClient,
+    paths,
+    prelude::*,
+    retry::RetryingFetcher,
+    session::VBulletinSession,
+};
+
+/// Fetcher a source gets its pages with: logged in if it has credentials,
+/// with reddit's own user agent unless the config sets one, recorded or
+/// replayed according to `mode`, and retrying transient failures
+fn source_fetcher(
+    config: &Config,
+    source: &SourceConfig,
+    http: &Arc<HttpClient>,
+    mode: &FetchMode,
+) -> Result<Arc<dyn Fetcher>> {
+    let live: Arc<dyn Fetcher> = match (source.kind.vbulletin(), &source.login) {
+        (Some((site, _)), Some(login)) => Arc::new(
+            VBulletinSession::new(
+                http,
+                site.base_url,
+                login.clone(),
+                paths::cookies_path(&source.database()),
+            )?
+            .logged_in_marker(site.logged_in_marker),
+        ),
+        (None, Some(_)) => {
+            return Err(WatchError::Config(format!(
+                "{} doesn't support login",
+                source.database()
+            )))
+        }
+        (_, None) => match (&source.kind, &config.politeness.user_agent) {
+            (SourceKind::Reddit { .. }, None) => Arc::new(http.with_user_agent(REDDIT_USER_AGENT)?),
+            _ => http.clone(),
+        },
+    };
+
+    Ok(Arc::new(RetryingFetcher::new(
+        mode.fetcher(live),
+        config.retry.clone(),
+    )))
+}
+
+fn forum<S: ForumSite>(
+    source: &SourceConfig,
+    site: S,
+    fetcher: &Arc<dyn Fetcher>,
+) -> Result<Box<dyn Scraper>> {
+    let mut scraper = Forum::new(site, source.database()).fetcher(fetcher.clone());
+
+    if let Some(max_pages) = source.max_pages {
+        scraper = scraper.max_pages(max_pages);
+    }
+
+    if let Some(concurrency) = source.concurrency {
+        scraper = scraper.concurrency(concurrency);
+    }
+
+    Ok(Box::new(scraper))
+}
+
+/// Creates the scraper described by a config source
+pub fn build_source(source: &SourceConfig, fetcher: &Arc<dyn Fetcher>) -> Result<Box<dyn Scraper>> {
+    match &source.kind {
+        SourceKind::RolexForums { forum_id } => forum(
+            source,
+            VBulletinSite::new(VBulletinConfig::rolex_forums(), *forum_id)?,
+            fetcher,
+        ),
+        SourceKind::Vbulletin { forum_id, site } => forum(
+            source,
+            VBulletinSite::new(site.clone(), *forum_id)?,
+            fetcher,
+        ),
+        SourceKind::Xenforo { forum: id, site } => {
+            forum(source, XenForoSite::new(site.clone(), id)?, fetcher)
+        }
+        SourceKind::Selectors { site } => forum(source, SelectorSite::new(site.clone())?, fetcher),
+        SourceKind::Reddit {
+            subreddit,
+            dumps,
+            site,
+        } => {
+            let mut scraper = RedditListing::new(site.clone(), subreddit, source.database())
+                .fetcher(fetcher.clone());
+
+            if let Some(dumps) = dumps {
+                scraper = scraper.dumps(dumps);
+            }
+
+            if let Some(max_pages) = source.max_pages {
+                scraper = scraper.max_pages(max_pages);
+            }
+
+            Ok(Box::new(scraper))
+        }
+        SourceKind::ProductOffers {
+            urls,
+            product_links,
+        } => {
+            let mut scraper =
+                ProductOffers::new(urls.clone(), source.database()).fetcher(fetcher.clone());
+
+            if let Some(selector) = product_links {
+                scraper = scraper.product_links(selector)?;
+            }
+
+            if let Some(concurrency) = source.concurrency {
+                scraper = scraper.concurrency(concurrency);
+            }
+
+            Ok(Box::new(scraper))
+        }
+        SourceKind::Feed { url, file } => {
+            let mut scraper = Feed::new(url, source.database()).fetcher(fetcher.clone());
+
+            if let Some(file) = file {
+                scraper = scraper.file(file);
+            }
+
+            Ok(Box::new(scraper))
+        }
+    }
+}
+
+/// Creates every enabled scraper of the config with its schedule. All of them
+/// share one HTTP client, so rate limits hold per host across scrapers.
+pub fn build(config: &Config, mode: FetchMode) -> Result<Vec<(Box<dyn Scraper>, Schedule)>> {
+    let http = Arc::new(HttpClient::new(config.politeness.clone())?);
+
+    config
+        .sources
+        .iter()
+        .filter(|source| source.enabled)
+        .map(|source| {
+            let fetcher = source_fetcher(config, source, &http, &mode)?;
+
+            Ok((build_source(source, &fetcher)?, source.schedule.schedule()))
+        })
+        .collect()
+}
@@ -0,0 +1,111 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use tokio::time::sleep;
+
+use crate::{fetch::Fetcher, prelude::*};
+
+/// How often and how long to wait before trying a failed request again. Only
+/// errors that may go away on their own are retried.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// tries including the first one
+    pub attempts: u32,
+    /// wait before the first retry in milliseconds, doubled for every retry
+    /// after it
+    pub base_delay_ms: u64,
+    /// upper bound for any wait, including ones asked for by Retry-After
+    pub max_delay_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            base_delay_ms: 2000,
+            max_delay_secs: 5 * 60,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry` (one based) after `error`
+    pub fn delay(&self, retry: u32, error: &WatchError) -> Duration {
+        let backoff = Duration::from_millis(self.base_delay_ms)
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+
+        error
+            .retry_after()
+            .unwrap_or(backoff)
+            .min(Duration::from_secs(self.max_delay_secs))
+    }
+
+    /// Runs `op` until it succeeds, fails permanently or runs out of attempts
+    pub async fn run<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match op().await {
+                Err(e) if e.is_retryable() && attempt < self.attempts => {
+                    let delay = self.delay(attempt, &e);
+
+                    eprintln!(
+                        "{e}, retry {attempt}/{} in {}s",
+                        self.attempts - 1,
+                        delay.as_secs_f32()
+                    );
+
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl WatchError {
+    /// The same request may succeed if tried again later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            WatchError::Request(e) => {
+                e.is_timeout() || e.is_connect() || e.is_request() || e.is_body()
+            }
+            WatchError::HttpStatus { status, .. } => {
+                *status == 408 || *status == 429 || *status >= 500
+            }
+            _ => false,
+        }
+    }
+
+    /// Wait asked for by the server with a Retry-After header
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            WatchError::HttpStatus { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// Retries failed fetches of another fetcher according to a `RetryPolicy`
+pub struct RetryingFetcher {
+    inner: Arc<dyn Fetcher>,
+    policy: RetryPolicy,
+}
+
+impl RetryingFetcher {
+    pub fn new(inner: Arc<dyn Fetcher>, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+impl Fetcher for RetryingFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(self.policy.run(move || self.inner.fetch(url)))
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    attributes::{find_attributes, WatchAttributes},
+    catalog::{Catalog, ModelInfo},
+    currency::extract_currency_to_usd,
+    identify::{identify_watch, Identification},
+    listing_kind::{classify_listing, ListingKind},
+    prelude::*,
+    tokenize::tokenize_watch_info,
+};
+
+/// One listing of a watch, shared by every source
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PriceEntry {
+    pub id: u64,
+    pub timestamp: i64,
+    pub price: Option<u32>,
+    pub is_sold: bool,
+    /// a sale was agreed but hasn't completed
+    #[serde(default)]
+    pub is_pending: bool,
+    /// sale, trade or want to buy. Only sales have asking prices. None for
+    /// entries stored before listings were told apart, they may be any kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ListingKind>,
+    pub brand: Box<str>,
+    pub model_no: Box<str>,
+    /// where an imported price was observed, an auction house for example
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Box<str>>,
+    /// model family, "Submariner", for price series of titles without a
+    /// reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<Box<str>>,
+    /// case size, material and such as the listing gives them
+    #[serde(default, skip_serializing_if = "WatchAttributes::is_empty")]
+    pub attributes: WatchAttributes,
+    /// what the reference catalog knows about the reference, None when it
+    /// isn't in the catalog
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<ModelInfo>,
+    /// how sure the brand and reference found are, from 0 to 1. None for
+    /// entries stored before it was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    /// the title didn't identify the watch surely enough to trust it
+    #[serde(default)]
+    pub needs_review: bool,
+}
+
+impl PriceEntry {
+    /// Entry for a listing whose title names a brand, None otherwise.
+    /// Listings without a reference, or with a brand or reference that isn't
+    /// certain enough, are kept and marked for review.
+    pub fn from_title(id: u64, timestamp: i64, title: &str) -> Option<Self> {
+        let identification = identify_watch(&tokenize_watch_info(title), Catalog::global());
+
+        Self::identified(id, timestamp, title, &identification)
+    }
+
+    /// Entry for a listing of the watch `identification` found, None when it
+    /// found no brand. The kind of listing and the attributes of the watch
+    /// are read from `title`.
+    pub fn identified(
+        id: u64,
+        timestamp: i64,
+        title: &str,
+        identification: &Identification,
+    ) -> Option<Self> {
+        let brand = identification.brand()?;
+        let reference = identification.reference();
+
+        Some(Self {
+            id,
+            timestamp,
+            price: None,
+            is_sold: false,
+            is_pending: false,
+            kind: Some(classify_listing(title)),
+            brand: brand.into(),
+            model_no: reference.map(|r| r.model_no.clone()).unwrap_or_default(),
+            collection: identification.collection.map(Into::into),
+            attributes: find_attributes(&tokenize_watch_info(title)),
+            origin: None,
+            model: reference.and_then(|r| r.watch).map(|w| w.info.clone()),
+            confidence: Some(identification.confidence() as f32),
+            needs_review: identification.needs_review(),
+        })
+    }
+
+    /// Updates the sold state and price from one line of a listing's text.
+    /// Later lines win, so edits further down a thread replace the asking
+    /// price.
+    pub fn read_line(&mut self, line: &str) {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            return;
+        }
+
+        let lower = trimmed.to_lowercase();
+
+        if lower.contains("sold")
+            && !lower.contains("not sold")
+            // isn't sold
+            // isnt sold
+            && !lower.contains("nt sold")
+        {
+            self.is_sold = true;
+        }
+
+        if let Ok(currency) = extract_currency_to_usd(self.timestamp, trimmed) {
+            self.price = Some(currency);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use chrono::DateTime;
+use tokio::sync::Mutex;
+
+use crate::{
+    fetch::{stable_hash, Fetcher},
+    http::HttpClient,
+    prelude::*,
+};
+
+// feeds only repeat their latest items, far fewer than this
+pub const SEEN_CAPACITY: usize = 10_000;
+
+/// Guids of the feed items already read. Once there are `SEEN_CAPACITY` of
+/// them the oldest are forgotten.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct SeenIds {
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+}
+
+impl SeenIds {
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Remembers `id`, returns whether it wasn't seen before
+    pub fn insert(&mut self, id: &str) -> bool {
+        if !self.ids.insert(id.to_owned()) {
+            return false;
+        }
+
+        self.order.push_back(id.to_owned());
+
+        while self.order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+impl From<Vec<String>> for SeenIds {
+    fn from(ids: Vec<String>) -> Self {
+        let mut seen = Self::default();
+
+        ids.iter().for_each(|id| {
+            seen.insert(id);
+        });
+
+        seen
+    }
+}
+
+impl From<SeenIds> for Vec<String> {
+    fn from(seen: SeenIds) -> Self {
+        seen.order.into()
+    }
+}
+
+/// An RSS `<item>` or Atom `<entry>`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FeedItem {
+    /// `<guid>` or `<id>`, the link or title of items without one
+    pub guid: String,
+    pub title: String,
+    /// html of the description, summary or content
+    pub description: String,
+    /// publication time of the item, unix seconds
+    pub published: Option<i64>,
+}
+
+impl FeedItem {
+    /// Entry for an item about an identifiable watch. The price is read from
+    /// the title and then the text of the description.
+    pub fn to_entry(&self, timestamp: i64) -> Option<PriceEntry> {
+        let mut entry = PriceEntry::from_title(
+            stable_hash(&self.guid),
+            self.published.unwrap_or(timestamp),
+            &self.title,
+        )?;
+
+        entry.read_line(&self.title);
+
+        let description = Html::parse_fragment(&self.description);
+
+        for line in description.root_element().text() {
+            for line in line.lines() {
+                entry.read_line(line);
+            }
+        }
+
+        Some(entry)
+    }
+}
+
+/// Text of the first child element named `name`, namespaces are ignored
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.tag_name().name() == name)
+        .and_then(|n| n.text())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn rss_item(item: roxmltree::Node) -> FeedItem {
+    let title = child_text(item, "title").unwrap_or_default();
+
+    FeedItem {
+        guid: child_text(item, "guid")
+            .or_else(|| child_text(item, "link"))
+            .unwrap_or(title)
+            .to_owned(),
+        title: title.to_owned(),
+        description: child_text(item, "description")
+            .or_else(|| child_text(item, "encoded"))
+            .unwrap_or_default()
+            .to_owned(),
+        published: child_text(item, "pubDate")
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.timestamp()),
+    }
+}
+
+fn atom_entry(entry: roxmltree::Node) -> FeedItem {
+    let title = child_text(entry, "title").unwrap_or_default();
+    // the link of an atom entry is an attribute
+    let link = entry
+        .children()
+        .find(|n| n.tag_name().name() == "link")
+        .and_then(|n| n.attribute("href"));
+
+    FeedItem {
+        guid: child_text(entry, "id").or(link).unwrap_or(title).to_owned(),
+        title: title.to_owned(),
+        description: child_text(entry, "summary")
+            .or_else(|| child_text(entry, "content"))
+            .unwrap_or_default()
+            .to_owned(),
+        published: child_text(entry, "published")
+            .or_else(|| child_text(entry, "updated"))
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.timestamp()),
+    }
+}
+
+/// Items of an RSS 2.0 or Atom feed, newest first as feeds list them
+pub fn parse_feed(s: &str) -> Result<Vec<FeedItem>> {
+    let doc = roxmltree::Document::parse(s)?;
+    let root = doc.root_element();
+
+    match root.tag_name().name() {
+        "rss" => Ok(root
+            .children()
+            .filter(|n| n.tag_name().name() == "channel")
+            .flat_map(|channel| channel.children())
+            .filter(|n| n.tag_name().name() == "item")
+            .map(rss_item)
+            .collect()),
+        "feed" => Ok(root
+            .children()
+            .filter(|n| n.tag_name().name() == "entry")
+            .map(atom_entry)
+            .collect()),
+        _ => Err(WatchError::MissingElement("rss or feed".to_owned())),
+    }
+}
+
+/// New threads or listings of an RSS or Atom feed. Every poll only reads the
+/// items it hasn't seen before, so the feed can be polled often.
+pub struct Feed {
+    url: String,
+    file: Option<PathBuf>,
+    name: String,
+    fetcher: Arc<dyn Fetcher>,
+    db: Arc<Mutex<PriceDatabase<PriceEntry>>>,
+}
+
+impl Feed {
+    pub fn new(url: impl Into<String>, name: impl Into<String>) -> Self {
+        let name = name.into();
+
+        Self {
+            url: url.into(),
+            file: None,
+            db: Arc::new(Mutex::new(PriceDatabase::new(name.clone()))),
+            name,
+            fetcher: Arc::new(HttpClient::default()),
+        }
+    }
+
+    /// Read the feed from this file instead of fetching it
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// Pages are fetched with `fetcher`, share it between scrapers to share
+    /// its per-host rate limits
+    pub fn fetcher(mut self, fetcher: Arc<dyn Fetcher>) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    /// Adds the items not seen before to the database, returns how many of
+    /// them were about an identifiable watch
+    pub fn merge_items(data: &mut PriceDatabase<PriceEntry>, items: &[FeedItem]) -> usize {
+        let now = Utc::now().timestamp();
+        let mut added = 0;
+
+        for item in items {
+            // items about other things are remembered too, so they aren't
+            // identified again on every poll
+            if !data.seen.insert(&item.guid) {
+                continue;
+            }
+
+            let Some(entry) = item.to_entry(now) else {
+                continue;
+            };
+
+            println!("{}", entry.model_no);
+
+            match data.entries.iter_mut().find(|e| e.id == entry.id) {
+                Some(e) => *e = entry,
+                None => data.entries.push(entry),
+            }
+
+            added += 1;
+        }
+
+        added
+    }
+}
+
+impl Scraper for Feed {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn update(&mut self) -> AsyncResult<()> {
+        let url = self.url.clone();
+        let file = self.file.clone();
+        let fetcher = self.fetcher.clone();
+        let db = self.db.clone();
+
+        Box::pin(async move {
+            let mut data = db.lock().await;
+
+            data.load().await?;
+
+            println!("Name: {}", data.name);
+
+            let s = match &file {
+                Some(file) => tokio::fs::read_to_string(file).await?,
+                None => fetcher.fetch(&url).await?,
+            };
+            let items = parse_feed(&s)?;
+            let added = Self::merge_items(&mut data, &items);
+
+            println!("{added} new of {} items", items.len());
+
+            println!("Sorting data by timestamp...");
+            data.entries.sort_by_key(|x| x.timestamp);
+
+            println!("Done!");
+
+            // beeeeeeeep!
+            beep();
+
+            data.save().await?;
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,428 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone};
+use futures::{stream, StreamExt};
+use tokio::sync::Mutex;
+
+use crate::{fetch::Fetcher, http::HttpClient, prelude::*};
+
+// deepest page a full scrape walks back to
+pub const FORUM_MAX_PAGES: usize = 1000;
+
+// threads fetched at the same time, request spacing per host is still enforced
+// by the fetcher
+pub const FORUM_CONCURRENCY: usize = 4;
+
+// list pages failing in a row that end an update, more of them mean the
+// site is down or logged us out rather than a few bad pages
+pub const FORUM_MAX_FAILED_PAGES: usize = 3;
+
+// stands in for the page number or thread id of a url when archived urls are
+// matched against `list_url` and `thread_url`
+const URL_MARKER: usize = 918_273_645;
+
+/// A page of a forum section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForumPage {
+    List(usize),
+    Thread(u64),
+}
+
+/// Layout of a forum section: numbered pages of threads, newest activity
+/// first, and a page per thread with its price and sold state
+pub trait ForumSite: Send + Sync + 'static {
+    fn list_url(&self, page: usize) -> String;
+
+    /// Parses a thread list page into entries and the number of pages.
+    /// Relative dates such as "Today" are relative to `now`.
+    fn parse_page_at(
+        &self,
+        s: &str,
+        page: usize,
+        now: DateTime<Local>,
+    ) -> Result<(Vec<PriceEntry>, usize)>;
+
+    /// Parses a thread list page fetched just now
+    fn parse_page(&self, s: &str, page: usize) -> Result<(Vec<PriceEntry>, usize)> {
+        self.parse_page_at(s, page, Local::now())
+    }
+
+    fn thread_url(&self, id: u64) -> String;
+
+    /// Reads sold state and price from a thread page
+    fn read_thread(&self, s: &str, entry: &mut PriceEntry);
+
+    /// Which page of this forum section `url` is, if any
+    fn page_of(&self, url: &str) -> Option<ForumPage> {
+        if let Some(page) = match_url(&self.list_url(URL_MARKER), url) {
+            return Some(ForumPage::List(page.map_or(1, |page| page as usize)));
+        }
+
+        match_url(&self.thread_url(URL_MARKER as u64), url)
+            .flatten()
+            .map(ForumPage::Thread)
+    }
+}
+
+/// Unix time of a date on a forum page, tried with each of `formats`. Dates
+/// starting with the today or yesterday label of `labels` are followed by a
+/// time in `time_format` and are relative to `now`.
+pub fn parse_forum_date(
+    input: &str,
+    formats: &[String],
+    time_format: &str,
+    (today_label, yesterday_label): (&str, &str),
+    now: DateTime<Local>,
+) -> Result<i64> {
+    let relative_day = |label: &str, days_ago: u64| -> Option<Result<NaiveDateTime>> {
+        let time_part = input.strip_prefix(label)?.trim();
+
+        Some(
+            NaiveTime::parse_from_str(time_part, time_format)
+                .map(|time| {
+                    let date = now.date_naive() - chrono::Days::new(days_ago);
+                    NaiveDateTime::new(date, time)
+                })
+                .map_err(Into::into),
+        )
+    };
+
+    let datetime = match relative_day(today_label, 0).or_else(|| relative_day(yesterday_label, 1)) {
+        Some(datetime) => datetime?,
+        None => formats
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+            .ok_or(WatchError::ParseTime)?,
+    };
+
+    // Convert NaiveDateTime to a DateTime<Utc>
+    match Utc.from_local_datetime(&datetime) {
+        chrono::offset::LocalResult::Single(t) => Ok(t.timestamp()),
+        _ => Err(WatchError::ParseTime),
+    }
+}
+
+/// Matches `url` against a url with `URL_MARKER` in place of a number, and
+/// returns that number if the url has one. Scheme, "www.", trailing slashes
+/// and query parameters that don't tell pages apart are ignored.
+fn match_url(pattern: &str, url: &str) -> Option<Option<u64>> {
+    let pattern = reqwest::Url::parse(pattern).ok()?;
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = |url: &reqwest::Url| {
+        url.host_str()
+            .map(|h| h.trim_start_matches("www.").to_owned())
+    };
+
+    if host(&pattern) != host(&url) {
+        return None;
+    }
+
+    let marker = URL_MARKER.to_string();
+    let capture = |pattern: &str, s: &str| -> Option<Option<u64>> {
+        match pattern.split_once(marker.as_str()) {
+            None => (pattern == s).then_some(None),
+            // XenForo has a slug in front of thread ids, "title.123"
+            Some((prefix, suffix)) => s
+                .strip_prefix(prefix)?
+                .strip_suffix(suffix)?
+                .rsplit('.')
+                .next()?
+                .parse()
+                .ok()
+                .map(Some),
+        }
+    };
+
+    let pattern_path = pattern.path().trim_end_matches('/');
+    let path = url.path().trim_end_matches('/');
+    let mut number = match capture(pattern_path, path) {
+        Some(number) => number,
+        // first pages often leave out their page segment, "/forums/x/" for
+        // "/forums/x/page-1"
+        None => {
+            let (rest, last) = pattern_path.rsplit_once('/')?;
+
+            if rest != path || !last.contains(marker.as_str()) {
+                return None;
+            }
+
+            None
+        }
+    };
+
+    for (key, value) in pattern.query_pairs() {
+        let Some((_, url_value)) = url.query_pairs().find(|(k, _)| *k == key) else {
+            continue;
+        };
+
+        if value.contains(marker.as_str()) {
+            number = Some(capture(&value, &url_value)??);
+        } else if value != url_value && value.parse::<u64>().is_ok() {
+            // fixed numbers like the forum id must match, sort options and
+            // the like may differ
+            return None;
+        }
+    }
+
+    Some(number)
+}
+
+/// Scrapes a forum section page by page until it reaches threads that
+/// haven't changed since the last update
+pub struct Forum<S> {
+    site: Arc<S>,
+    name: String,
+    max_pages: usize,
+    concurrency: usize,
+    fetcher: Arc<dyn Fetcher>,
+    db: Arc<Mutex<PriceDatabase<PriceEntry>>>,
+}
+
+impl<S: ForumSite> Forum<S> {
+    pub fn new(site: S, name: impl Into<String>) -> Self {
+        let name = name.into();
+
+        Self {
+            site: Arc::new(site),
+            db: Arc::new(Mutex::new(PriceDatabase::new(name.clone()))),
+            name,
+            max_pages: FORUM_MAX_PAGES,
+            concurrency: FORUM_CONCURRENCY,
+            fetcher: Arc::new(HttpClient::default()),
+        }
+    }
+
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Maximum number of threads fetched at the same time
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Pages are fetched with `fetcher`, share it between scrapers to share
+    /// its per-host rate limits
+    pub fn fetcher(mut self, fetcher: Arc<dyn Fetcher>) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    fn fetch_page(
+        site: Arc<S>,
+        fetcher: Arc<dyn Fetcher>,
+        page: usize,
+    ) -> AsyncResult<(Vec<PriceEntry>, usize)> {
+        Box::pin(async move {
+            let url = site.list_url(page);
+
+            let s = fetcher.fetch(&url).await?;
+
+            match site.parse_page(&s, page) {
+                Err(e) if e.is_parse() => {
+                    save_diagnostic(&url, &s).await;
+                    Err(e)
+                }
+                result => result,
+            }
+        })
+    }
+
+    /// Adds the entries of a thread list page to the database, fetching the
+    /// threads of new or changed entries. Returns the number of unchanged
+    /// entries.
+    pub(crate) async fn merge_entries(
+        data: &mut PriceDatabase<PriceEntry>,
+        site: &Arc<S>,
+        fetcher: &Arc<dyn Fetcher>,
+        entries: Vec<PriceEntry>,
+        concurrency: usize,
+    ) -> usize {
+        let mut unchanged_entry_count = 0;
+
+        // entries that are new or have new posts need their thread parsed
+        let mut stale_entries = Vec::new();
+
+        for entry in entries {
+            match data.entries.iter().find(|e| e.id == entry.id) {
+                // entry exists but unchanged. Some sites mark sold threads in
+                // the list without a new post, that is a change as well
+                Some(e)
+                    if e.timestamp == entry.timestamp
+                        && (e.is_sold || !entry.is_sold)
+                        && (e.is_pending || !entry.is_pending) =>
+                {
+                    unchanged_entry_count += 1;
+                }
+                _ => stale_entries.push(entry),
+            }
+        }
+
+        // fetch threads concurrently. `buffered` yields in page order, so
+        // merging stays deterministic
+        let updated: Vec<_> = stream::iter(stale_entries)
+            .map(|mut entry| {
+                let fetcher = fetcher.clone();
+
+                async move {
+                    let result = fetcher
+                        .fetch(&site.thread_url(entry.id))
+                        .await
+                        .map(|s| site.read_thread(&s, &mut entry));
+
+                    (entry, result)
+                }
+            })
+            .buffered(concurrency)
+            .collect()
+            .await;
+
+        for (entry, result) in updated {
+            if let Err(e) = result {
+                println!("{e}");
+            } else {
+                println!("{}", entry.model_no);
+            }
+
+            match data.entries.iter_mut().find(|e| e.id == entry.id) {
+                // entry existed before, but we have new posts to parse
+                Some(e) => *e = entry,
+                // entry doesnt exist and needs to be fully parsed or newly created
+                None => data.entries.push(entry),
+            }
+        }
+
+        unchanged_entry_count
+    }
+
+    /// Remembers a page that couldn't be scraped so the next update tries it
+    /// again. Pages that don't exist are dropped.
+    fn skip_page(data: &mut PriceDatabase<PriceEntry>, page: usize, e: WatchError) {
+        eprintln!("Skipping page {page}: {e}");
+
+        if !matches!(e, WatchError::HttpStatus { status: 404, .. })
+            && !data.skipped_pages.contains(&page)
+        {
+            data.skipped_pages.push(page);
+        }
+    }
+}
+
+impl<S: ForumSite> Scraper for Forum<S> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn update(&mut self) -> AsyncResult<()> {
+        let site = self.site.clone();
+        let max_pages = self.max_pages;
+        let concurrency = self.concurrency;
+        let fetcher = self.fetcher.clone();
+        let db = self.db.clone();
+
+        Box::pin(async move {
+            // the database stays in memory between updates, it is only read
+            // from disk the first time
+            let mut data = db.lock().await;
+
+            data.load().await?;
+
+            // if this is the first time, start from this first page
+            // or the next available
+            if data.position == 0 {
+                data.position = 1;
+            }
+
+            let mut max_page = max_pages.max(data.position + 1);
+            let mut unchanged_pages_sequence = 0;
+            // the page count is only a guess until a list page was read
+            let mut page_count_known = false;
+            let mut failed_pages = Vec::new();
+
+            println!("Name: {}", data.name);
+
+            // pages that failed on earlier updates
+            for page in std::mem::take(&mut data.skipped_pages) {
+                match Self::fetch_page(site.clone(), fetcher.clone(), page).await {
+                    Ok((entries, _)) => {
+                        println!("Revisited skipped page {page}");
+                        Self::merge_entries(&mut data, &site, &fetcher, entries, concurrency).await;
+                    }
+                    Err(e) => Self::skip_page(&mut data, page, e),
+                }
+            }
+
+            while data.position <= max_page {
+                // save progress every 10 pages in case of unexpected events
+                if data.position % 10 == 0 {
+                    data.save().await?;
+                }
+
+                // transient errors were already retried by the fetcher
+                let page = data.position;
+
+                match Self::fetch_page(site.clone(), fetcher.clone(), page).await {
+                    Ok((entries, new_max_page)) => {
+                        let num_page_entries = entries.len();
+                        let unchanged_entry_count =
+                            Self::merge_entries(&mut data, &site, &fetcher, entries, concurrency)
+                                .await;
+
+                        if unchanged_entry_count == num_page_entries && num_page_entries != 0 {
+                            unchanged_pages_sequence += 1;
+                        } else {
+                            unchanged_pages_sequence = 0;
+                        }
+
+                        max_page = new_max_page.min(max_pages);
+                        page_count_known = true;
+                        failed_pages.clear();
+                    }
+                    Err(e)
+                        if !page_count_known
+                            || failed_pages.len() + 1 >= FORUM_MAX_FAILED_PAGES =>
+                    {
+                        // the next update starts over from the first failed
+                        // page instead of revisiting every one of them
+                        data.skipped_pages.retain(|p| !failed_pages.contains(p));
+                        data.position = failed_pages.first().copied().unwrap_or(page);
+                        data.save().await?;
+
+                        return Err(e);
+                    }
+                    Err(e) => {
+                        failed_pages.push(page);
+                        Self::skip_page(&mut data, page, e);
+                    }
+                }
+
+                data.position += 1;
+
+                if unchanged_pages_sequence == 1 {
+                    println!("Reached last changed page!");
+                    break;
+                }
+            }
+
+            println!("Resetting page data...");
+            data.position = 0;
+
+            println!("Removing duplicate entries...");
+            data.entries.dedup_by_key(|x| x.id);
+
+            println!("Sorting data by timestamp...");
+            data.entries.sort_by_key(|x| x.timestamp);
+
+            println!("Done!");
+
+            // beeeeeeeep!
+            beep();
+
+            data.save().await?;
+
+            Ok(())
+        })
+    }
+}
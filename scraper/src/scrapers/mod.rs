@@ -1,18 +1,33 @@
 use std::{
     cell::Cell,
     future::Future,
+    path::{Path, PathBuf},
     pin::Pin,
     sync::atomic::{AtomicI64, AtomicU64},
     sync::Arc,
 };
 
-use crate::prelude::*;
+use scraper::{selectable::Selectable, ElementRef};
 
-pub(crate) mod other;
-pub(crate) mod rolex_forums;
+use crate::{fetch::corpus_file_name, paths, prelude::*};
 
-pub(crate) use other::*;
-pub(crate) use rolex_forums::*;
+pub(crate) mod entry;
+pub(crate) mod feed;
+pub(crate) mod forum;
+pub(crate) mod product_offers;
+pub(crate) mod reddit;
+pub(crate) mod selector_site;
+pub(crate) mod vbulletin;
+pub(crate) mod xenforo;
+
+pub(crate) use entry::*;
+pub(crate) use feed::*;
+pub(crate) use forum::*;
+pub(crate) use product_offers::*;
+pub(crate) use reddit::*;
+pub(crate) use selector_site::*;
+pub(crate) use vbulletin::*;
+pub(crate) use xenforo::*;
 
 #[derive(Serialize, Deserialize)]
 pub struct PriceDatabase<T> {
@@ -23,54 +38,229 @@ pub struct PriceDatabase<T> {
     // can be used if we need to start scraper from the very beginning again
     // or to scrape from where we left off
     pub position: usize,
+
+    // pages that still failed after retrying, revisited on the next update
+    #[serde(default)]
+    pub skipped_pages: Vec<usize>,
+
+    // guids of the feed items already read, feeds repeat their recent items
+    // on every poll
+    #[serde(default, skip_serializing_if = "SeenIds::is_empty")]
+    pub seen: SeenIds,
+
+    // set once the database has been read from disk, so long-running scrapers
+    // keep their in-memory state instead of re-reading it on every update
+    #[serde(skip)]
+    pub loaded: bool,
 }
 
+impl<T> PriceDatabase<T> {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            timestamp: 0.into(),
+            entries: Vec::new(),
+            position: 0,
+            skipped_pages: Vec::new(),
+            seen: SeenIds::default(),
+            loaded: false,
+        }
+    }
+}
+
+// previous versions of a database kept next to it as `{name}.json.1` (newest)
+// to `{name}.json.N`
+pub const DB_BACKUP_GENERATIONS: usize = 3;
+
 impl<T> PriceDatabase<T>
 where
     T: Serialize + for<'a> Deserialize<'a>,
 {
+    /// Saves the database in the data dir, see `save_in`
     pub async fn save(&self) -> Result<()> {
+        self.save_in(paths::data_dir()).await
+    }
+
+    /// Writes the database to a temporary file in `dir` first and renames it
+    /// over the old one, so a crash never leaves a truncated database behind.
+    /// The previous version is kept as a backup generation.
+    pub async fn save_in(&self, dir: &Path) -> Result<()> {
         use std::sync::atomic::Ordering;
+        use tokio::io::AsyncWriteExt;
 
-        if !tokio::fs::try_exists("data/").await? {
-            tokio::fs::create_dir("data/").await?;
-        }
+        tokio::fs::create_dir_all(dir).await?;
 
         let now_time = Utc::now().timestamp();
 
         self.timestamp.store(now_time, Ordering::SeqCst);
 
-        tokio::fs::write(
-            format!("data/{}.json", self.name),
-            serde_json::to_string(self)?,
-        )
-        .await?;
+        let path = paths::db_path(dir, &self.name);
+        let tmp_path = path.with_extension("json.tmp");
+
+        let mut file = tokio::fs::File::create(&tmp_path).await?;
+        file.write_all(serde_json::to_string(self)?.as_bytes())
+            .await?;
+        file.sync_all().await?;
+
+        // shift backups one generation back, dropping the oldest
+        for generation in (1..DB_BACKUP_GENERATIONS).rev() {
+            match tokio::fs::rename(
+                paths::db_backup_path(dir, &self.name, generation),
+                paths::db_backup_path(dir, &self.name, generation + 1),
+            )
+            .await
+            {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+
+        // copy rather than move, the database file must exist at all times
+        if DB_BACKUP_GENERATIONS > 0 && tokio::fs::try_exists(&path).await? {
+            tokio::fs::copy(&path, paths::db_backup_path(dir, &self.name, 1)).await?;
+        }
+
+        tokio::fs::rename(&tmp_path, &path).await?;
 
         println!("Saved {} DB, {} entries", self.name, self.entries.len());
 
         Ok(())
     }
 
-    pub async fn try_load(&mut self) -> Result<()> {
-        let s = tokio::fs::read_to_string(format!("data/{}.json", self.name)).await?;
+    async fn try_load_from(&mut self, path: &Path) -> Result<()> {
+        let s = tokio::fs::read_to_string(path).await?;
         *self = serde_json::from_str(&s)?;
 
         Ok(())
     }
 
+    pub async fn try_load(&mut self, dir: &Path) -> Result<()> {
+        self.try_load_from(&paths::db_path(dir, &self.name)).await
+    }
+
+    /// Loads the newest backup generation in `dir` that can be read
+    async fn try_recover(&mut self, dir: &Path) -> Option<PathBuf> {
+        for generation in 1..=DB_BACKUP_GENERATIONS {
+            let path = paths::db_backup_path(dir, &self.name, generation);
+
+            match self.try_load_from(&path).await {
+                Ok(()) => return Some(path),
+                Err(WatchError::IO(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => println!("Skipping backup {}: {e}", path.display()),
+            }
+        }
+
+        None
+    }
+
+    /// Loads the database from the data dir, see `load_in`
     pub async fn load(&mut self) -> Result<()> {
-        match self.try_load().await {
+        self.load_in(paths::data_dir()).await
+    }
+
+    /// Loads the database in `dir` unless it is already in memory. A missing
+    /// or corrupt database falls back to the newest valid backup.
+    pub async fn load_in(&mut self, dir: &Path) -> Result<()> {
+        if self.loaded {
+            return Ok(());
+        }
+
+        match self.try_load(dir).await {
             Ok(()) => {
                 println!("Loaded {} DB, {} entries", self.name, self.entries.len());
+            }
+            Err(e) => {
+                let missing =
+                    matches!(&e, WatchError::IO(io) if io.kind() == std::io::ErrorKind::NotFound);
 
-                Ok(())
+                // only a missing or unparsable database is worth recovering
+                if !missing && !matches!(e, WatchError::Json(_)) {
+                    return Err(e);
+                }
+
+                let path = paths::db_path(dir, &self.name);
+
+                match self.try_recover(dir).await {
+                    Some(backup) => {
+                        // keep the broken file for inspection, but out of the
+                        // backup rotation
+                        if !missing {
+                            tokio::fs::rename(&path, path.with_extension("json.corrupt")).await?;
+                        }
+
+                        let saved = chrono::DateTime::from_timestamp(
+                            self.timestamp.load(std::sync::atomic::Ordering::Relaxed),
+                            0,
+                        )
+                        .unwrap_or_default();
+
+                        println!(
+                            "Recovered {} DB from {} ({e}), {} entries saved {saved}",
+                            self.name,
+                            backup.display(),
+                            self.entries.len(),
+                        );
+                    }
+                    None if missing => {}
+                    None => return Err(e),
+                }
             }
-            Err(WatchError::IO(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
-            Err(e) => Err(e),
         }
+
+        self.loaded = true;
+
+        Ok(())
+    }
+}
+
+/// Selector from a config file
+pub fn parse_selector(s: &str) -> Result<Selector> {
+    Selector::parse(s).map_err(|e| WatchError::Config(format!("invalid selector {s:?}: {e}")))
+}
+
+/// Element `n` (zero based) matching `selector`, or an error naming the
+/// selector so a layout change is easy to track down
+pub fn select_nth<'a, S: Selectable<'a>>(
+    scope: S,
+    selector: &Selector,
+    n: usize,
+    name: &str,
+) -> Result<ElementRef<'a>> {
+    scope
+        .select(selector)
+        .nth(n)
+        .ok_or_else(|| WatchError::MissingElement(name.to_owned()))
+}
+
+/// First text node of an element
+pub fn first_text<'a>(element: ElementRef<'a>, field: &'static str) -> Result<&'a str> {
+    element.text().nth(0).ok_or(WatchError::ParseField {
+        field,
+        value: element.html(),
+    })
+}
+
+/// Keeps a page that failed to parse so selectors can be fixed against it
+pub async fn save_diagnostic(url: &str, html: &str) {
+    let dir = paths::diagnostics_dir();
+    let path = dir.join(format!(
+        "{}-{}",
+        Utc::now().format("%Y%m%d-%H%M%S"),
+        corpus_file_name(url)
+    ));
+
+    let result = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&path, html).await
+    };
+
+    match result.await {
+        Ok(()) => eprintln!("Saved unparsable page {url} to {}", path.display()),
+        Err(e) => eprintln!("Failed to save unparsable page {url}: {e}"),
     }
 }
 
-pub trait Scraper {
+pub trait Scraper: Send {
+    fn name(&self) -> &str;
     fn update(&mut self) -> AsyncResult<()>;
 }
@@ -0,0 +1,422 @@
+use std::sync::Arc;
+
+use futures::{stream, StreamExt};
+use scraper::ElementRef;
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::{
+    catalog::Catalog,
+    currency::to_usd,
+    fetch::{stable_hash, Fetcher},
+    http::HttpClient,
+    identify::identify_fields,
+    prelude::*,
+    tokenize::tokenize_watch_info,
+};
+
+// product pages fetched at the same time
+pub const PRODUCT_OFFERS_CONCURRENCY: usize = 4;
+
+/// A product offer read from the structured data of a page, before the watch
+/// is identified
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Offer {
+    pub name: String,
+    pub brand: Option<String>,
+    pub sku: Option<String>,
+    pub mpn: Option<String>,
+    pub url: Option<String>,
+    pub price: Option<String>,
+    /// ISO 4217 code
+    pub currency: Option<String>,
+    pub is_sold: bool,
+}
+
+// schema.org availability values of something that can't be bought anymore
+const SOLD_AVAILABILITY: &[&str] = &["SoldOut", "OutOfStock", "Discontinued"];
+
+fn is_sold_availability(availability: &str) -> bool {
+    SOLD_AVAILABILITY
+        .iter()
+        .any(|sold| availability.ends_with(sold))
+}
+
+/// `@type` names without the vocabulary prefix, "http://schema.org/Product"
+/// is "Product"
+fn json_ld_types(object: &serde_json::Map<String, Value>) -> Vec<&str> {
+    let types = match object.get("@type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+
+    types
+        .into_iter()
+        .map(|t| t.rsplit(['/', ':']).next().unwrap_or(t))
+        .collect()
+}
+
+/// Text of a JSON-LD value, numbers included. Objects like a `Brand` give
+/// their name.
+fn json_ld_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_owned()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Object(object) => object.get("name").and_then(json_ld_text),
+        Value::Array(values) => values.iter().find_map(json_ld_text),
+        _ => None,
+    }
+}
+
+fn json_ld_offer(product: &serde_json::Map<String, Value>, offer: &Value) -> Offer {
+    let field = |name: &str| product.get(name).and_then(json_ld_text);
+    let offer_field = |name: &str| {
+        offer.get(name).or_else(|| {
+            offer
+                .get("priceSpecification")
+                .and_then(|spec| spec.get(name))
+        })
+    };
+
+    Offer {
+        name: field("name").unwrap_or_default(),
+        brand: field("brand"),
+        sku: field("sku"),
+        mpn: field("mpn"),
+        url: offer_field("url")
+            .or(product.get("url"))
+            .and_then(json_ld_text),
+        // aggregate offers only have a price range
+        price: offer_field("price")
+            .or(offer_field("lowPrice"))
+            .and_then(json_ld_text),
+        currency: offer_field("priceCurrency").and_then(json_ld_text),
+        is_sold: offer_field("availability")
+            .and_then(json_ld_text)
+            .is_some_and(|a| is_sold_availability(&a)),
+    }
+}
+
+fn collect_json_ld_offers(value: &Value, offers: &mut Vec<Offer>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                collect_json_ld_offers(value, offers);
+            }
+        }
+        Value::Object(object) => {
+            let types = json_ld_types(object);
+
+            if !types
+                .iter()
+                .any(|t| ["Product", "IndividualProduct", "ProductModel"].contains(t))
+            {
+                // products can be nested anywhere, in an `ItemList`, a
+                // `@graph` or the variants of a `ProductGroup`
+                for value in object.values() {
+                    collect_json_ld_offers(value, offers);
+                }
+
+                return;
+            }
+
+            match object.get("offers") {
+                Some(Value::Array(product_offers)) => {
+                    for offer in product_offers {
+                        offers.push(json_ld_offer(object, offer));
+                    }
+                }
+                Some(offer) => offers.push(json_ld_offer(object, offer)),
+                None => offers.push(json_ld_offer(object, &Value::Null)),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Offers of the `application/ld+json` blocks of a page. Blocks that aren't
+/// valid JSON are skipped.
+pub fn json_ld_offers(doc: &Html) -> Vec<Offer> {
+    lazy_static! {
+        static ref JSON_LD_SELECTOR: Selector =
+            Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    }
+
+    let mut offers = Vec::new();
+
+    for script in doc.select(&JSON_LD_SELECTOR) {
+        if let Ok(value) = serde_json::from_str::<Value>(&script.text().collect::<String>()) {
+            collect_json_ld_offers(&value, &mut offers);
+        }
+    }
+
+    offers
+}
+
+/// Value of the first `itemprop` matching `selector` in `scope`
+fn microdata_prop(scope: ElementRef, selector: &Selector) -> Option<String> {
+    let element = scope.select(selector).next()?;
+    let value = element
+        .attr("content")
+        .or(element.attr("href"))
+        .map(str::to_owned)
+        .unwrap_or_else(|| element.text().collect());
+
+    Some(value.trim().to_owned()).filter(|v| !v.is_empty())
+}
+
+/// Offers of the schema.org microdata of a page
+pub fn microdata_offers(doc: &Html) -> Vec<Offer> {
+    lazy_static! {
+        static ref PRODUCT_SELECTOR: Selector =
+            Selector::parse(r#"[itemscope][itemtype$="schema.org/Product"]"#).unwrap();
+        static ref OFFER_SELECTOR: Selector = Selector::parse(r#"[itemprop="offers"]"#).unwrap();
+        static ref NAME_SELECTOR: Selector = Selector::parse(r#"[itemprop="name"]"#).unwrap();
+        static ref BRAND_SELECTOR: Selector = Selector::parse(r#"[itemprop="brand"]"#).unwrap();
+        static ref SKU_SELECTOR: Selector = Selector::parse(r#"[itemprop="sku"]"#).unwrap();
+        static ref MPN_SELECTOR: Selector = Selector::parse(r#"[itemprop="mpn"]"#).unwrap();
+        static ref URL_SELECTOR: Selector = Selector::parse(r#"[itemprop="url"]"#).unwrap();
+        static ref PRICE_SELECTOR: Selector = Selector::parse(r#"[itemprop="price"]"#).unwrap();
+        static ref CURRENCY_SELECTOR: Selector =
+            Selector::parse(r#"[itemprop="priceCurrency"]"#).unwrap();
+        static ref AVAILABILITY_SELECTOR: Selector =
+            Selector::parse(r#"[itemprop="availability"]"#).unwrap();
+    }
+
+    doc.select(&PRODUCT_SELECTOR)
+        .map(|product| {
+            // price fields may sit directly on the product in sloppy markup
+            let offer = product.select(&OFFER_SELECTOR).next().unwrap_or(product);
+
+            // a brand is either plain text or a `Brand` item with a name
+            let brand = product.select(&BRAND_SELECTOR).next().and_then(|brand| {
+                microdata_prop(brand, &NAME_SELECTOR).or_else(|| {
+                    Some(brand.text().collect::<String>().trim().to_owned())
+                        .filter(|b| !b.is_empty())
+                })
+            });
+
+            Offer {
+                name: microdata_prop(product, &NAME_SELECTOR).unwrap_or_default(),
+                brand,
+                sku: microdata_prop(product, &SKU_SELECTOR),
+                mpn: microdata_prop(product, &MPN_SELECTOR),
+                url: microdata_prop(offer, &URL_SELECTOR)
+                    .or_else(|| microdata_prop(product, &URL_SELECTOR)),
+                price: microdata_prop(offer, &PRICE_SELECTOR),
+                currency: microdata_prop(offer, &CURRENCY_SELECTOR),
+                is_sold: microdata_prop(offer, &AVAILABILITY_SELECTOR)
+                    .is_some_and(|a| is_sold_availability(&a)),
+            }
+        })
+        .collect()
+}
+
+/// Structured offers of a page, JSON-LD if it has any and microdata otherwise
+pub fn page_offers(s: &str) -> Vec<Offer> {
+    let doc = Html::parse_document(s);
+    let offers = json_ld_offers(&doc);
+
+    match offers.is_empty() {
+        true => microdata_offers(&doc),
+        false => offers,
+    }
+}
+
+impl Offer {
+    /// Entry for an offer of a watch of a known brand seen at `timestamp`.
+    /// The reference comes from the mpn or sku when they look like one,
+    /// dealers often use their own stock numbers instead.
+    pub fn to_entry(&self, page_url: &str, timestamp: i64) -> Option<PriceEntry> {
+        let codes: Vec<_> = [&self.mpn, &self.sku]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        let identification = identify_fields(
+            &tokenize_watch_info(&self.name),
+            self.brand.as_deref(),
+            &codes,
+            Catalog::global(),
+        );
+
+        let price = match (&self.price, &self.currency) {
+            (Some(price), Some(currency)) => to_usd(timestamp, currency, price).ok(),
+            _ => None,
+        };
+
+        // one page can list several products without urls of their own
+        let key = format!(
+            "{}#{}",
+            self.url.as_deref().unwrap_or(page_url),
+            self.sku.as_deref().unwrap_or(&self.name)
+        );
+
+        let mut entry =
+            PriceEntry::identified(stable_hash(&key), timestamp, &self.name, &identification)?;
+
+        entry.price = price;
+        entry.is_sold = self.is_sold;
+
+        Some(entry)
+    }
+}
+
+/// Dealer pages with schema.org `Product` offers. Each configured page is
+/// read, and the product pages it links to when a link selector is set.
+pub struct ProductOffers {
+    urls: Vec<String>,
+    product_links: Option<Arc<Selector>>,
+    name: String,
+    concurrency: usize,
+    fetcher: Arc<dyn Fetcher>,
+    db: Arc<Mutex<PriceDatabase<PriceEntry>>>,
+}
+
+impl ProductOffers {
+    pub fn new(urls: Vec<String>, name: impl Into<String>) -> Self {
+        let name = name.into();
+
+        Self {
+            urls,
+            product_links: None,
+            db: Arc::new(Mutex::new(PriceDatabase::new(name.clone()))),
+            name,
+            concurrency: PRODUCT_OFFERS_CONCURRENCY,
+            fetcher: Arc::new(HttpClient::default()),
+        }
+    }
+
+    /// Links to product pages on the configured pages
+    pub fn product_links(mut self, selector: &str) -> Result<Self> {
+        self.product_links = Some(Arc::new(parse_selector(selector)?));
+        Ok(self)
+    }
+
+    /// Maximum number of product pages fetched at the same time
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Pages are fetched with `fetcher`, share it between scrapers to share
+    /// its per-host rate limits
+    pub fn fetcher(mut self, fetcher: Arc<dyn Fetcher>) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    /// Absolute urls of the product links of a page, without duplicates
+    fn links(page_url: &str, s: &str, selector: &Selector) -> Vec<String> {
+        let Ok(base) = reqwest::Url::parse(page_url) else {
+            return Vec::new();
+        };
+        let doc = Html::parse_document(s);
+        let mut links = Vec::new();
+
+        for link in doc.select(selector) {
+            if let Some(url) = link.attr("href").and_then(|href| base.join(href).ok()) {
+                let url = url.to_string();
+
+                if !links.contains(&url) {
+                    links.push(url);
+                }
+            }
+        }
+
+        links
+    }
+
+    /// Adds the offers of a page to the database. An offer keeps the time it
+    /// was first seen at until its price or availability changes.
+    fn merge_offers(data: &mut PriceDatabase<PriceEntry>, page_url: &str, offers: &[Offer]) {
+        let now = Utc::now().timestamp();
+
+        for offer in offers {
+            let Some(entry) = offer.to_entry(page_url, now) else {
+                continue;
+            };
+
+            match data.entries.iter_mut().find(|e| e.id == entry.id) {
+                Some(e) if e.price == entry.price && e.is_sold == entry.is_sold => {}
+                Some(e) => *e = entry,
+                None => {
+                    println!("{}", entry.model_no);
+                    data.entries.push(entry);
+                }
+            }
+        }
+    }
+}
+
+impl Scraper for ProductOffers {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn update(&mut self) -> AsyncResult<()> {
+        let urls = self.urls.clone();
+        let product_links = self.product_links.clone();
+        let concurrency = self.concurrency;
+        let fetcher = self.fetcher.clone();
+        let db = self.db.clone();
+
+        Box::pin(async move {
+            let mut data = db.lock().await;
+
+            data.load().await?;
+
+            println!("Name: {}", data.name);
+
+            for url in urls {
+                // one broken page shouldn't stop the others
+                let s = match fetcher.fetch(&url).await {
+                    Ok(s) => s,
+                    Err(e) => {
+                        eprintln!("Skipping {url}: {e}");
+                        continue;
+                    }
+                };
+
+                Self::merge_offers(&mut data, &url, &page_offers(&s));
+
+                let Some(selector) = &product_links else {
+                    continue;
+                };
+
+                let pages: Vec<_> = stream::iter(Self::links(&url, &s, selector))
+                    .map(|link| {
+                        let fetcher = fetcher.clone();
+
+                        async move {
+                            let result = fetcher.fetch(&link).await;
+                            (link, result)
+                        }
+                    })
+                    .buffered(concurrency)
+                    .collect()
+                    .await;
+
+                for (link, result) in pages {
+                    match result {
+                        Ok(s) => Self::merge_offers(&mut data, &link, &page_offers(&s)),
+                        Err(e) => eprintln!("Skipping {link}: {e}"),
+                    }
+                }
+            }
+
+            println!("Sorting data by timestamp...");
+            data.entries.sort_by_key(|x| x.timestamp);
+
+            println!("Done!");
+
+            // beeeeeeeep!
+            beep();
+
+            data.save().await?;
+
+            Ok(())
+        })
+    }
+}
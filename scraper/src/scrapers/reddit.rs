@@ -0,0 +1,324 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use tokio::sync::Mutex;
+
+use crate::{
+    fetch::Fetcher,
+    http::{HttpClient, Politeness},
+    prelude::*,
+};
+
+// reddit stops listings after about 1000 posts, 10 pages of 100
+pub const REDDIT_MAX_PAGES: usize = 10;
+
+// reddit throttles or blocks clients without a descriptive user agent, this
+// one is sent unless the config sets another
+pub const REDDIT_USER_AGENT: &str = concat!(
+    "watchinspect-data:",
+    env!("CARGO_PKG_VERSION"),
+    " (watch price research)"
+);
+
+/// Marketplace subreddit settings. The defaults match r/Watchexchange.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedditConfig {
+    pub base_url: String,
+    /// title tags of posts offering a watch, compared case insensitively
+    pub sale_tags: Vec<String>,
+    pub sold_flairs: Vec<String>,
+    pub pending_flairs: Vec<String>,
+}
+
+impl Default for RedditConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://www.reddit.com".to_owned(),
+            sale_tags: vec!["WTS".to_owned(), "WTT".to_owned()],
+            sold_flairs: vec!["Sold".to_owned()],
+            pending_flairs: vec!["Pending".to_owned()],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Listing {
+    data: ListingData,
+}
+
+#[derive(Deserialize)]
+struct ListingData {
+    after: Option<String>,
+    children: Vec<Thing>,
+}
+
+#[derive(Deserialize)]
+struct Thing {
+    data: Post,
+}
+
+#[derive(Deserialize)]
+struct Post {
+    /// base 36
+    id: String,
+    title: String,
+    #[serde(default)]
+    selftext: String,
+    link_flair_text: Option<String>,
+    created_utc: f64,
+}
+
+/// Tags in square brackets at the start of a title, "[WTS/WTT] ..." has WTS
+/// and WTT
+fn title_tags(title: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut rest = title.trim_start();
+
+    while let Some((tag, after)) = rest.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        tags.extend(tag.split(['/', ',', ' ']).filter(|t| !t.is_empty()));
+        rest = after.trim_start();
+    }
+
+    tags
+}
+
+/// New posts of a subreddit, read from its JSON listing or from listing files
+/// dumped earlier
+pub struct RedditListing {
+    config: RedditConfig,
+    subreddit: String,
+    dumps: Option<PathBuf>,
+    name: String,
+    max_pages: usize,
+    fetcher: Arc<dyn Fetcher>,
+    db: Arc<Mutex<PriceDatabase<PriceEntry>>>,
+}
+
+impl RedditListing {
+    pub fn new(
+        config: RedditConfig,
+        subreddit: impl Into<String>,
+        name: impl Into<String>,
+    ) -> Self {
+        let name = name.into();
+
+        Self {
+            config,
+            subreddit: subreddit.into(),
+            dumps: None,
+            db: Arc::new(Mutex::new(PriceDatabase::new(name.clone()))),
+            name,
+            max_pages: REDDIT_MAX_PAGES,
+            fetcher: Arc::new(
+                HttpClient::new(Politeness {
+                    user_agent: Some(REDDIT_USER_AGENT.to_owned()),
+                    ..Default::default()
+                })
+                .unwrap(),
+            ),
+        }
+    }
+
+    /// Read listings from this file, or every `.json` file in this directory,
+    /// instead of fetching them
+    pub fn dumps(mut self, path: impl Into<PathBuf>) -> Self {
+        self.dumps = Some(path.into());
+        self
+    }
+
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Pages are fetched with `fetcher`, share it between scrapers to share
+    /// its per-host rate limits
+    pub fn fetcher(mut self, fetcher: Arc<dyn Fetcher>) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    /// Page of the newest posts, continuing after the `after` cursor of the
+    /// previous page
+    pub fn listing_url(config: &RedditConfig, subreddit: &str, after: Option<&str>) -> String {
+        let mut url = format!(
+            "{}/r/{subreddit}/new.json?limit=100&raw_json=1",
+            config.base_url
+        );
+
+        if let Some(after) = after {
+            url.push_str("&after=");
+            url.push_str(after);
+        }
+
+        url
+    }
+
+    /// Parses a listing into entries for the sale posts and the cursor of the
+    /// next page
+    pub fn parse_listing(
+        config: &RedditConfig,
+        s: &str,
+    ) -> Result<(Vec<PriceEntry>, Option<String>)> {
+        let listing: Listing = serde_json::from_str(s)?;
+        let mut entries = Vec::new();
+
+        for Thing { data: post } in listing.data.children {
+            let is_sale = title_tags(&post.title)
+                .iter()
+                .any(|tag| config.sale_tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+
+            // buyers and meta posts have no asking price
+            if !is_sale {
+                continue;
+            }
+
+            let id = u64::from_str_radix(&post.id, 36).map_err(|_| WatchError::ParseField {
+                field: "post id",
+                value: post.id.clone(),
+            })?;
+
+            let Some(mut entry) = PriceEntry::from_title(id, post.created_utc as i64, &post.title)
+            else {
+                continue;
+            };
+
+            let flair = post.link_flair_text.as_deref().unwrap_or_default().trim();
+            let is = |flairs: &[String]| flairs.iter().any(|f| f.eq_ignore_ascii_case(flair));
+
+            entry.is_sold = is(&config.sold_flairs);
+            entry.is_pending = is(&config.pending_flairs);
+
+            // the price is in the title or the text, the text wins
+            entry.read_line(&post.title);
+
+            for line in post.selftext.lines() {
+                entry.read_line(line);
+            }
+
+            entries.push(entry);
+        }
+
+        Ok((entries, listing.data.after))
+    }
+
+    /// Every listing file of the dump path, in name order
+    async fn dump_files(path: &Path) -> Result<Vec<PathBuf>> {
+        if !tokio::fs::metadata(path).await?.is_dir() {
+            return Ok(vec![path.to_owned()]);
+        }
+
+        let mut files = Vec::new();
+        let mut dir = tokio::fs::read_dir(path).await?;
+
+        while let Some(entry) = dir.next_entry().await? {
+            if entry.path().extension().is_some_and(|e| e == "json") {
+                files.push(entry.path());
+            }
+        }
+
+        files.sort();
+
+        Ok(files)
+    }
+
+    async fn read_dump(config: &RedditConfig, file: &Path) -> Result<Vec<PriceEntry>> {
+        let s = tokio::fs::read_to_string(file).await?;
+        let (entries, _) = Self::parse_listing(config, &s)?;
+
+        Ok(entries)
+    }
+
+    fn merge_entries(data: &mut PriceDatabase<PriceEntry>, entries: Vec<PriceEntry>) {
+        for entry in entries {
+            println!("{}", entry.model_no);
+
+            match data.entries.iter_mut().find(|e| e.id == entry.id) {
+                Some(e) => *e = entry,
+                None => data.entries.push(entry),
+            }
+        }
+    }
+}
+
+impl Scraper for RedditListing {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn update(&mut self) -> AsyncResult<()> {
+        let config = self.config.clone();
+        let subreddit = self.subreddit.clone();
+        let dumps = self.dumps.clone();
+        let max_pages = self.max_pages;
+        let fetcher = self.fetcher.clone();
+        let db = self.db.clone();
+
+        Box::pin(async move {
+            let mut data = db.lock().await;
+
+            data.load().await?;
+
+            println!("Name: {}", data.name);
+
+            if let Some(dumps) = dumps {
+                let mut files_read = 0;
+                let mut first_error = None;
+
+                // one bad file doesn't lose the rest of the dump
+                for file in Self::dump_files(&dumps).await? {
+                    match Self::read_dump(&config, &file).await {
+                        Ok(entries) => {
+                            println!("Read {}", file.display());
+                            Self::merge_entries(&mut data, entries);
+                            files_read += 1;
+                        }
+                        Err(e) => {
+                            eprintln!("Skipping {}: {e}", file.display());
+                            first_error.get_or_insert(e);
+                        }
+                    }
+                }
+
+                if let (0, Some(e)) = (files_read, first_error) {
+                    return Err(e);
+                }
+            } else {
+                // the whole listing is walked every time, flairs of older
+                // posts change to sold without the post moving up
+                let mut after = None;
+
+                for page in 1..=max_pages {
+                    println!("PAGE: {page}/{max_pages}");
+
+                    let url = Self::listing_url(&config, &subreddit, after.as_deref());
+                    let s = fetcher.fetch(&url).await?;
+                    let (entries, next) = Self::parse_listing(&config, &s)?;
+
+                    Self::merge_entries(&mut data, entries);
+
+                    after = next;
+
+                    if after.is_none() {
+                        break;
+                    }
+                }
+            }
+
+            println!("Sorting data by timestamp...");
+            data.entries.sort_by_key(|x| x.timestamp);
+
+            println!("Done!");
+
+            // beeeeeeeep!
+            beep();
+
+            data.save().await?;
+
+            Ok(())
+        })
+    }
+}
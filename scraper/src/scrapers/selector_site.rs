@@ -0,0 +1,315 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local};
+use regex::Regex;
+use scraper::ElementRef;
+
+use crate::{fetch::stable_hash, prelude::*};
+
+/// Any site described by selectors in the config
+pub type SelectorForum = Forum<SelectorSite>;
+
+/// Where a value is on a page: a selector for the text of the first match,
+/// or a table adding the attribute to read and a regex to cut the value out
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum FieldConfig {
+    Selector(String),
+    Field(FieldSpec),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FieldSpec {
+    pub selector: String,
+    /// attribute holding the value, the text when omitted
+    pub attr: Option<String>,
+    /// the first capture group, or the whole match, is the value
+    pub pattern: Option<String>,
+}
+
+/// Layout of a site with a list of threads or listings and a page for each
+/// one, for sites without an engine of their own
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SelectorConfig {
+    /// list page, `{page}` is filled in
+    pub list_url: String,
+    /// thread or listing page, `{id}` is filled in
+    pub thread_url: String,
+    /// a thread on a list page, the fields are looked up inside it
+    pub row_selector: String,
+    pub title: FieldConfig,
+    /// number or slug identifying the thread, cut out of a link with
+    /// `pattern`. It fills in `{id}` of `thread_url`.
+    pub id: FieldConfig,
+    /// time of the last post
+    pub date: FieldConfig,
+    /// formats of the date, tried in order. "%s" reads unix timestamps
+    #[serde(default = "default_date_formats")]
+    pub date_formats: Vec<String>,
+    /// format of the time after `today_label` and `yesterday_label`
+    #[serde(default = "default_time_format")]
+    pub time_format: String,
+    #[serde(default = "default_today_label")]
+    pub today_label: String,
+    #[serde(default = "default_yesterday_label")]
+    pub yesterday_label: String,
+    /// number of pages, "Page 2 of 57" or the last page link
+    pub page_count: Option<FieldConfig>,
+    /// link to the next page, for sites that don't show the page count.
+    /// Without either only the first page is read.
+    pub next_page_selector: Option<String>,
+    /// rows to leave out, stickies or ads
+    pub skip_selector: Option<String>,
+    /// rows matching or containing a match of these are sold or pending
+    pub sold_selector: Option<String>,
+    pub pending_selector: Option<String>,
+    /// posts of a thread page, each one is read for prices
+    pub post_selector: String,
+}
+
+fn default_date_formats() -> Vec<String> {
+    vec!["%Y-%m-%d %H:%M".to_owned(), "%s".to_owned()]
+}
+
+fn default_time_format() -> String {
+    "%H:%M".to_owned()
+}
+
+fn default_today_label() -> String {
+    "Today".to_owned()
+}
+
+fn default_yesterday_label() -> String {
+    "Yesterday".to_owned()
+}
+
+/// A `FieldConfig` with its selector and pattern parsed
+struct Field {
+    name: &'static str,
+    selector_str: String,
+    selector: Selector,
+    attr: Option<String>,
+    pattern: Option<Regex>,
+}
+
+impl Field {
+    fn new(name: &'static str, config: &FieldConfig) -> Result<Self> {
+        let spec = match config {
+            FieldConfig::Selector(selector) => FieldSpec {
+                selector: selector.clone(),
+                attr: None,
+                pattern: None,
+            },
+            FieldConfig::Field(spec) => spec.clone(),
+        };
+
+        Ok(Self {
+            name,
+            selector: parse_selector(&spec.selector)?,
+            selector_str: spec.selector,
+            attr: spec.attr,
+            pattern: spec
+                .pattern
+                .map(|pattern| {
+                    Regex::new(&pattern).map_err(|e| {
+                        WatchError::Config(format!("invalid {name} pattern {pattern:?}: {e}"))
+                    })
+                })
+                .transpose()?,
+        })
+    }
+
+    /// Value of the field in `scope`, whitespace collapsed
+    fn extract(&self, scope: ElementRef) -> Result<String> {
+        let element = select_nth(scope, &self.selector, 0, &self.selector_str)?;
+        let value = match &self.attr {
+            Some(attr) => element.attr(attr).unwrap_or_default().to_owned(),
+            None => element.text().collect::<Vec<_>>().join(" "),
+        };
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        let Some(pattern) = &self.pattern else {
+            return Ok(value);
+        };
+
+        pattern
+            .captures(&value)
+            .and_then(|c| c.get(1).or_else(|| c.get(0)))
+            .map(|m| m.as_str().to_owned())
+            .ok_or(WatchError::ParseField {
+                field: self.name,
+                value,
+            })
+    }
+}
+
+/// A `SelectorConfig` site, with its selectors parsed
+pub struct SelectorSite {
+    pub config: SelectorConfig,
+    row: Selector,
+    title: Field,
+    id: Field,
+    date: Field,
+    page_count: Option<Field>,
+    next_page: Option<Selector>,
+    skip: Option<Selector>,
+    sold: Option<Selector>,
+    pending: Option<Selector>,
+    post: Selector,
+    /// ids of the threads with a slug for an id, and their slug
+    slugs: Mutex<HashMap<u64, String>>,
+}
+
+impl SelectorSite {
+    pub fn new(config: SelectorConfig) -> Result<Self> {
+        let optional =
+            |selector: &Option<String>| selector.as_deref().map(parse_selector).transpose();
+
+        Ok(Self {
+            row: parse_selector(&config.row_selector)?,
+            title: Field::new("thread title", &config.title)?,
+            id: Field::new("thread id", &config.id)?,
+            date: Field::new("last post date", &config.date)?,
+            page_count: config
+                .page_count
+                .as_ref()
+                .map(|page_count| Field::new("page count", page_count))
+                .transpose()?,
+            next_page: optional(&config.next_page_selector)?,
+            skip: optional(&config.skip_selector)?,
+            sold: optional(&config.sold_selector)?,
+            pending: optional(&config.pending_selector)?,
+            post: parse_selector(&config.post_selector)?,
+            slugs: Mutex::new(HashMap::new()),
+            config,
+        })
+    }
+}
+
+impl ForumSite for SelectorSite {
+    fn list_url(&self, page: usize) -> String {
+        self.config.list_url.replace("{page}", &page.to_string())
+    }
+
+    fn parse_page_at(
+        &self,
+        s: &str,
+        page: usize,
+        now: DateTime<Local>,
+    ) -> Result<(Vec<PriceEntry>, usize)> {
+        let config = &self.config;
+        let doc = Html::parse_document(s);
+        let rows: Vec<_> = doc.select(&self.row).collect();
+
+        // error and login pages have no rows
+        if rows.is_empty() {
+            return Err(WatchError::MissingElement(config.row_selector.clone()));
+        }
+
+        let max_page = match (&self.page_count, &self.next_page) {
+            (Some(page_count), _) => {
+                let label = page_count.extract(doc.root_element())?;
+
+                label
+                    .split_whitespace()
+                    .last()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .ok_or(WatchError::ParseField {
+                        field: "page count",
+                        value: label,
+                    })?
+            }
+            (None, Some(next_page)) if doc.select(next_page).next().is_some() => page + 1,
+            _ => page,
+        };
+
+        println!("PAGE: {page}/{max_page}");
+
+        let mut entries = Vec::new();
+        // a row matches when it or an element inside it does
+        let matches = |row: ElementRef, selector: &Option<Selector>| {
+            selector.as_ref().is_some_and(|selector| {
+                selector.matches(&row) || row.select(selector).next().is_some()
+            })
+        };
+
+        let mut rows_read = 0;
+        let mut row_error = None;
+
+        for row in rows {
+            if matches(row, &self.skip) {
+                continue;
+            }
+
+            let fields = self.id.extract(row).and_then(|id| {
+                let timestamp = parse_forum_date(
+                    &self.date.extract(row)?,
+                    &config.date_formats,
+                    &config.time_format,
+                    (&config.today_label, &config.yesterday_label),
+                    now,
+                )?;
+
+                Ok((id, self.title.extract(row)?, timestamp))
+            });
+
+            // ads and promos look like rows but have no id or date
+            let (id, title, timestamp) = match fields {
+                Ok(fields) => {
+                    rows_read += 1;
+                    fields
+                }
+                Err(e) => {
+                    row_error.get_or_insert(e);
+                    continue;
+                }
+            };
+
+            let id = match id.parse::<u64>() {
+                Ok(id) => id,
+                Err(_) => {
+                    let hash = stable_hash(&id);
+
+                    self.slugs.lock().unwrap().insert(hash, id);
+                    hash
+                }
+            };
+
+            let Some(mut entry) = PriceEntry::from_title(id, timestamp, &title) else {
+                continue;
+            };
+
+            entry.is_sold = matches(row, &self.sold);
+            entry.is_pending = matches(row, &self.pending);
+
+            entries.push(entry);
+        }
+
+        // but when no row can be read the layout changed
+        if let (0, Some(e)) = (rows_read, row_error) {
+            return Err(e);
+        }
+
+        Ok((entries, max_page))
+    }
+
+    fn thread_url(&self, id: u64) -> String {
+        let slug = self.slugs.lock().unwrap().get(&id).cloned();
+
+        self.config
+            .thread_url
+            .replace("{id}", &slug.unwrap_or_else(|| id.to_string()))
+    }
+
+    fn read_thread(&self, s: &str, entry: &mut PriceEntry) {
+        let doc = Html::parse_document(s);
+
+        for post in doc.select(&self.post) {
+            for line in post.text() {
+                entry.read_line(line);
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use chrono::{DateTime, Local};
+
+use crate::prelude::*;
+
+pub const ROLEX_FORUMS_URL: &str = "https://www.rolexforums.com";
+
+/// Sub-forum of a vBulletin site listing watches for sale
+pub type VBulletinForum = Forum<VBulletinSite>;
+
+/// Layout of a vBulletin forum. The defaults match a stock vBulletin 3 theme,
+/// so most sites only need `base_url`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct VBulletinConfig {
+    pub base_url: String,
+    /// thread list page, `{base_url}`, `{forum_id}` and `{page}` are filled in
+    pub list_url: String,
+    /// thread page, `{base_url}` and `{id}` are filled in
+    pub thread_url: String,
+    /// container of the thread list, missing on error and login pages
+    pub forum_selector: String,
+    /// element whose text ends with the number of pages, "Page 2 of 57"
+    pub page_count_selector: String,
+    pub thread_list_selector: String,
+    pub row_selector: String,
+    /// thread title link, its id is `title_id_prefix` followed by the thread id
+    pub title_selector: String,
+    pub title_id_prefix: String,
+    /// cell holding the last post date, the `last_post_index`th match in a row
+    pub last_post_selector: String,
+    pub last_post_index: usize,
+    pub date_selector: String,
+    pub time_selector: String,
+    pub post_selector: String,
+    /// formats of "{date} {time}" of the last post, tried in order
+    pub date_formats: Vec<String>,
+    /// format of the time after `today_label` and `yesterday_label`
+    pub time_format: String,
+    pub today_label: String,
+    pub yesterday_label: String,
+    /// rows at the top of the first page that are sticky threads
+    pub sticky_rows: usize,
+    /// rows matching this are sticky threads on any page, for themes that mark
+    /// them
+    pub sticky_selector: Option<String>,
+    /// text only found on pages shown to logged in members
+    pub logged_in_marker: String,
+}
+
+impl Default for VBulletinConfig {
+    fn default() -> Self {
+        Self {
+            base_url: String::new(),
+            list_url: "{base_url}/forumdisplay.php?f={forum_id}&order=desc&page={page}".to_owned(),
+            thread_url: "{base_url}/showthread.php?t={id}".to_owned(),
+            forum_selector: "#inlinemodform".to_owned(),
+            page_count_selector: "td.vbmenu_control".to_owned(),
+            thread_list_selector: r#"[id^="threadbits_forum_"]"#.to_owned(),
+            row_selector: "tr".to_owned(),
+            title_selector: r#"[id^="thread_title_"]"#.to_owned(),
+            title_id_prefix: "thread_title_".to_owned(),
+            last_post_selector: "td.alt2".to_owned(),
+            last_post_index: 1,
+            date_selector: "div.smallfont".to_owned(),
+            time_selector: "span.time".to_owned(),
+            post_selector: r#"div[id^="post_message_"]"#.to_owned(),
+            // "21 July 2023 04:52 PM"
+            date_formats: vec!["%d %B %Y %I:%M %p".to_owned()],
+            time_format: "%I:%M %p".to_owned(),
+            today_label: "Today".to_owned(),
+            yesterday_label: "Yesterday".to_owned(),
+            sticky_rows: 0,
+            sticky_selector: None,
+            logged_in_marker: "login.php?do=logout".to_owned(),
+        }
+    }
+}
+
+impl VBulletinConfig {
+    /// rolexforums.com, whose first page starts with three sticky threads
+    pub fn rolex_forums() -> Self {
+        Self {
+            base_url: ROLEX_FORUMS_URL.to_owned(),
+            sticky_rows: 3,
+            ..Default::default()
+        }
+    }
+}
+
+/// A sub-forum of a `VBulletinConfig` site, with its selectors parsed
+pub struct VBulletinSite {
+    pub config: VBulletinConfig,
+    forum_id: usize,
+    forum: Selector,
+    page_count: Selector,
+    thread_list: Selector,
+    row: Selector,
+    title: Selector,
+    last_post: Selector,
+    date: Selector,
+    time: Selector,
+    post: Selector,
+    sticky: Option<Selector>,
+}
+
+impl VBulletinSite {
+    pub fn new(config: VBulletinConfig, forum_id: usize) -> Result<Self> {
+        if config.base_url.is_empty() {
+            return Err(WatchError::Config(
+                "vbulletin source without base_url".to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            forum_id,
+            forum: parse_selector(&config.forum_selector)?,
+            page_count: parse_selector(&config.page_count_selector)?,
+            thread_list: parse_selector(&config.thread_list_selector)?,
+            row: parse_selector(&config.row_selector)?,
+            title: parse_selector(&config.title_selector)?,
+            last_post: parse_selector(&config.last_post_selector)?,
+            date: parse_selector(&config.date_selector)?,
+            time: parse_selector(&config.time_selector)?,
+            post: parse_selector(&config.post_selector)?,
+            sticky: config
+                .sticky_selector
+                .as_deref()
+                .map(parse_selector)
+                .transpose()?,
+            config,
+        })
+    }
+}
+
+impl ForumSite for VBulletinSite {
+    fn list_url(&self, page: usize) -> String {
+        self.config
+            .list_url
+            .replace("{base_url}", &self.config.base_url)
+            .replace("{forum_id}", &self.forum_id.to_string())
+            .replace("{page}", &page.to_string())
+    }
+
+    fn parse_page_at(
+        &self,
+        s: &str,
+        page: usize,
+        now: DateTime<Local>,
+    ) -> Result<(Vec<PriceEntry>, usize)> {
+        let config = &self.config;
+        let doc = Html::parse_document(s);
+        let forum = select_nth(&doc, &self.forum, 0, &config.forum_selector)?;
+
+        let page_nav = select_nth(forum, &self.page_count, 0, &config.page_count_selector)?;
+        let page_pos_label = first_text(page_nav, "page number label")?;
+        let max_page = page_pos_label
+            .split_ascii_whitespace()
+            .last()
+            .and_then(|n| n.parse::<usize>().ok())
+            .ok_or_else(|| WatchError::ParseField {
+                field: "page count",
+                value: page_pos_label.to_owned(),
+            })?;
+
+        println!("PAGE: {page}/{max_page}");
+
+        let thread_list_body =
+            select_nth(forum, &self.thread_list, 0, &config.thread_list_selector)?;
+        let mut entries = Vec::new();
+
+        for (i, tr) in thread_list_body.select(&self.row).enumerate() {
+            // sticky threads stay on top and would look unchanged forever
+            if page == 1 && i < config.sticky_rows {
+                continue;
+            }
+
+            if let Some(sticky) = &self.sticky {
+                if tr.select(sticky).next().is_some() {
+                    continue;
+                }
+            }
+
+            let title = select_nth(tr, &self.title, 0, &config.title_selector)?;
+            let title_id = title.attr("id").unwrap_or_default();
+            let id = title_id
+                .strip_prefix(config.title_id_prefix.as_str())
+                .and_then(|id| id.parse::<u64>().ok())
+                .ok_or_else(|| WatchError::ParseField {
+                    field: "thread id",
+                    value: title_id.to_owned(),
+                })?;
+            let title = first_text(title, "thread title")?;
+            let last_post = select_nth(
+                tr,
+                &self.last_post,
+                config.last_post_index,
+                &config.last_post_selector,
+            )?;
+            let (date_time, time) =
+                match last_post.select(&self.date).nth(0).and_then(|date_time| {
+                    date_time.select(&self.time).nth(0).map(|t| (date_time, t))
+                }) {
+                    Some(x) => x,
+                    None => continue,
+                };
+
+            let date = first_text(date_time, "last post date")?.trim();
+            let time = first_text(time, "last post time")?;
+
+            let timestamp = parse_forum_date(
+                &format!("{date} {time}"),
+                &config.date_formats,
+                &config.time_format,
+                (&config.today_label, &config.yesterday_label),
+                now,
+            )?;
+
+            if let Some(entry) = PriceEntry::from_title(id, timestamp, title) {
+                entries.push(entry);
+            }
+        }
+
+        Ok((entries, max_page))
+    }
+
+    fn thread_url(&self, id: u64) -> String {
+        self.config
+            .thread_url
+            .replace("{base_url}", &self.config.base_url)
+            .replace("{id}", &id.to_string())
+    }
+
+    // every post counts, price changes are often posted as replies
+    fn read_thread(&self, s: &str, entry: &mut PriceEntry) {
+        let doc = Html::parse_document(s);
+
+        for post in doc.select(&self.post) {
+            for line in post.text() {
+                entry.read_line(line);
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use chrono::{DateTime, Local};
+
+use crate::{listing_kind::classify_listing, prelude::*};
+
+/// Sales forum of a XenForo 2 site
+pub type XenForoForum = Forum<XenForoSite>;
+
+/// Layout of a XenForo forum. The defaults match the stock XenForo 2 theme,
+/// so most sites only need `base_url`.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct XenForoConfig {
+    pub base_url: String,
+    /// thread list page, `{base_url}`, `{forum}` and `{page}` are filled in
+    pub list_url: String,
+    /// thread page, `{base_url}` and `{id}` are filled in
+    pub thread_url: String,
+    /// container of the normal threads, missing on error and login pages.
+    /// Sticky threads are in a container of their own.
+    pub thread_list_selector: String,
+    pub thread_selector: String,
+    /// class of a thread row, followed by the thread id
+    pub thread_id_class_prefix: String,
+    pub title_selector: String,
+    /// label in front of the title, "For Sale", "Sold", ...
+    pub prefix_selector: String,
+    /// element of the last post with its unix time in `data-time`
+    pub last_post_selector: String,
+    /// page links, the last one is the number of pages
+    pub page_nav_selector: String,
+    /// body of the first post, the listing itself
+    pub first_post_selector: String,
+    pub sold_prefixes: Vec<String>,
+    pub pending_prefixes: Vec<String>,
+}
+
+impl Default for XenForoConfig {
+    fn default() -> Self {
+        Self {
+            base_url: String::new(),
+            list_url: "{base_url}/forums/{forum}/page-{page}?order=last_post_date&direction=desc"
+                .to_owned(),
+            thread_url: "{base_url}/threads/{id}/".to_owned(),
+            thread_list_selector: ".js-threadList".to_owned(),
+            thread_selector: ".structItem--thread".to_owned(),
+            thread_id_class_prefix: "js-threadListItem-".to_owned(),
+            title_selector: ".structItem-title a[data-tp-primary]".to_owned(),
+            prefix_selector: ".structItem-title .label".to_owned(),
+            last_post_selector: "time.structItem-latestDate".to_owned(),
+            page_nav_selector: ".pageNav-main .pageNav-page".to_owned(),
+            first_post_selector: ".message--post .bbWrapper".to_owned(),
+            sold_prefixes: vec!["Sold".to_owned()],
+            pending_prefixes: vec!["Pending".to_owned()],
+        }
+    }
+}
+
+/// A forum of a `XenForoConfig` site, with its selectors parsed
+pub struct XenForoSite {
+    pub config: XenForoConfig,
+    /// forum path segment, "sales-corner.12"
+    forum: String,
+    thread_list: Selector,
+    thread: Selector,
+    title: Selector,
+    prefix: Selector,
+    last_post: Selector,
+    page_nav: Selector,
+    first_post: Selector,
+}
+
+impl XenForoSite {
+    pub fn new(config: XenForoConfig, forum: impl Into<String>) -> Result<Self> {
+        if config.base_url.is_empty() {
+            return Err(WatchError::Config(
+                "xenforo source without base_url".to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            forum: forum.into(),
+            thread_list: parse_selector(&config.thread_list_selector)?,
+            thread: parse_selector(&config.thread_selector)?,
+            title: parse_selector(&config.title_selector)?,
+            prefix: parse_selector(&config.prefix_selector)?,
+            last_post: parse_selector(&config.last_post_selector)?,
+            page_nav: parse_selector(&config.page_nav_selector)?,
+            first_post: parse_selector(&config.first_post_selector)?,
+            config,
+        })
+    }
+}
+
+impl ForumSite for XenForoSite {
+    fn list_url(&self, page: usize) -> String {
+        self.config
+            .list_url
+            .replace("{base_url}", &self.config.base_url)
+            .replace("{forum}", &self.forum)
+            .replace("{page}", &page.to_string())
+    }
+
+    // last post times are absolute
+    fn parse_page_at(
+        &self,
+        s: &str,
+        page: usize,
+        _now: DateTime<Local>,
+    ) -> Result<(Vec<PriceEntry>, usize)> {
+        let config = &self.config;
+        let doc = Html::parse_document(s);
+        let thread_list = select_nth(&doc, &self.thread_list, 0, &config.thread_list_selector)?;
+
+        // forums with a single page have no page links
+        let max_page = doc
+            .select(&self.page_nav)
+            .last()
+            .map(|page_link| {
+                let label = page_link.text().collect::<String>();
+
+                label
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| WatchError::ParseField {
+                        field: "page count",
+                        value: label,
+                    })
+            })
+            .transpose()?
+            .unwrap_or(1);
+
+        println!("PAGE: {page}/{max_page}");
+
+        let mut entries = Vec::new();
+
+        for thread in thread_list.select(&self.thread) {
+            let id = thread
+                .value()
+                .classes()
+                .find_map(|class| class.strip_prefix(config.thread_id_class_prefix.as_str()))
+                .and_then(|id| id.parse::<u64>().ok())
+                .ok_or_else(|| WatchError::ParseField {
+                    field: "thread id",
+                    value: thread.attr("class").unwrap_or_default().to_owned(),
+                })?;
+            let title = select_nth(thread, &self.title, 0, &config.title_selector)?;
+            let title = title.text().collect::<String>();
+
+            // moved threads have no last post
+            let Some(last_post) = thread.select(&self.last_post).next() else {
+                continue;
+            };
+            let data_time = last_post.attr("data-time").unwrap_or_default();
+            let timestamp = data_time
+                .parse::<i64>()
+                .map_err(|_| WatchError::ParseField {
+                    field: "last post time",
+                    value: data_time.to_owned(),
+                })?;
+
+            let Some(mut entry) = PriceEntry::from_title(id, timestamp, &title) else {
+                continue;
+            };
+
+            let mut labels = Vec::new();
+
+            for prefix in thread.select(&self.prefix) {
+                let prefix = prefix.text().collect::<String>();
+                let is = |prefixes: &[String]| {
+                    prefixes
+                        .iter()
+                        .any(|p| p.eq_ignore_ascii_case(prefix.trim()))
+                };
+
+                entry.is_sold |= is(&config.sold_prefixes);
+                entry.is_pending |= is(&config.pending_prefixes);
+                labels.push(prefix);
+            }
+
+            // a "Wanted" or "For Trade" prefix is often all that says so
+            if !labels.is_empty() {
+                labels.push(title);
+                entry.kind = Some(classify_listing(&labels.join(" ")));
+            }
+
+            entries.push(entry);
+        }
+
+        Ok((entries, max_page))
+    }
+
+    fn thread_url(&self, id: u64) -> String {
+        self.config
+            .thread_url
+            .replace("{base_url}", &self.config.base_url)
+            .replace("{id}", &id.to_string())
+    }
+
+    // only the listing itself, replies quote prices of other watches
+    fn read_thread(&self, s: &str, entry: &mut PriceEntry) {
+        let doc = Html::parse_document(s);
+
+        if let Some(post) = doc.select(&self.first_post).next() {
+            for line in post.text() {
+                entry.read_line(line);
+            }
+        }
+    }
+}
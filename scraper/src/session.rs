@@ -0,0 +1,178 @@
+use std::{io::BufReader, path::PathBuf, sync::Arc, time::Instant};
+
+use cookie_store::CookieStore;
+use futures::future::BoxFuture;
+use reqwest_cookie_store::CookieStoreMutex;
+use tokio::{io::AsyncWriteExt, sync::Mutex};
+
+use crate::{fetch::Fetcher, http::HttpClient, prelude::*};
+
+/// Forum account of a source. The password can be read from an environment
+/// variable instead, so it doesn't have to live in the config file.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Login {
+    pub username: String,
+    pub password: Option<String>,
+    pub password_env: Option<String>,
+}
+
+impl Login {
+    pub fn password(&self) -> Result<String> {
+        if let Some(password) = &self.password {
+            return Ok(password.clone());
+        }
+
+        self.password_env
+            .as_ref()
+            .and_then(|var| std::env::var(var).ok())
+            .ok_or_else(|| WatchError::Config(format!("no password for {}", self.username)))
+    }
+}
+
+/// Logged in vBulletin browsing. Cookies are kept on disk so a restart doesn't
+/// need a new login, and pages served to guests trigger a login and refetch.
+pub struct VBulletinSession {
+    http: HttpClient,
+    base_url: String,
+    login: Login,
+    cookies: Arc<CookieStoreMutex>,
+    cookies_path: PathBuf,
+    logged_in_marker: String,
+    // time of the last login, also serializes logins of concurrent fetches
+    last_login: Mutex<Option<Instant>>,
+}
+
+impl VBulletinSession {
+    pub fn new(
+        http: &HttpClient,
+        base_url: impl Into<String>,
+        login: Login,
+        cookies_path: impl Into<PathBuf>,
+    ) -> Result<Self> {
+        let cookies_path = cookies_path.into();
+
+        let store = match std::fs::File::open(&cookies_path) {
+            Ok(file) => cookie_store::serde::json::load(BufReader::new(file))
+                .map_err(|e| WatchError::Cookies(e.to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CookieStore::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let cookies = Arc::new(CookieStoreMutex::new(store));
+
+        Ok(Self {
+            http: http.with_cookies(cookies.clone())?,
+            base_url: base_url.into(),
+            login,
+            cookies,
+            cookies_path,
+            logged_in_marker: "login.php?do=logout".to_owned(),
+            last_login: Mutex::new(None),
+        })
+    }
+
+    /// Text only found on pages of logged in members, a logout link by
+    /// default
+    pub fn logged_in_marker(mut self, marker: impl Into<String>) -> Self {
+        self.logged_in_marker = marker.into();
+        self
+    }
+
+    pub fn is_logged_out(&self, page: &str) -> bool {
+        !page.contains(&self.logged_in_marker)
+    }
+
+    pub async fn log_in(&self) -> Result<()> {
+        let password = self.login.password()?;
+        let page = self
+            .http
+            .post_form(
+                &format!("{}/login.php?do=login", self.base_url),
+                &[
+                    ("do", "login"),
+                    ("vb_login_username", &self.login.username),
+                    ("vb_login_password", &password),
+                    ("cookieuser", "1"),
+                    ("securitytoken", "guest"),
+                    ("s", ""),
+                ],
+            )
+            .await?;
+
+        if !page.contains("Thank you for logging in") {
+            return Err(WatchError::LoginFailed(self.login.username.clone()));
+        }
+
+        println!("Logged in to {} as {}", self.base_url, self.login.username);
+
+        self.save_cookies().await
+    }
+
+    async fn save_cookies(&self) -> Result<()> {
+        let mut json = Vec::new();
+
+        cookie_store::serde::json::save_incl_expired_and_nonpersistent(
+            &self.cookies.lock().unwrap(),
+            &mut json,
+        )
+        .map_err(|e| WatchError::Cookies(e.to_string()))?;
+
+        if let Some(dir) = self.cookies_path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+
+        // the cookies log in as the account, only its owner may read them
+        let mut options = tokio::fs::OpenOptions::new();
+
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let mut file = options.open(&self.cookies_path).await?;
+
+        // a jar saved before keeps its permissions otherwise
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))
+                .await?;
+        }
+
+        file.write_all(&json).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+impl Fetcher for VBulletinSession {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let page = self.http.get_text(url).await?;
+
+            if !self.is_logged_out(&page) {
+                return Ok(page);
+            }
+
+            {
+                let mut last_login = self.last_login.lock().await;
+
+                // another fetch may have logged in while this one was running
+                if last_login.map_or(true, |t| t < started) {
+                    self.log_in().await?;
+                    *last_login = Some(Instant::now());
+                }
+            }
+
+            let page = self.http.get_text(url).await?;
+
+            if self.is_logged_out(&page) {
+                return Err(WatchError::NotLoggedIn(url.to_owned()));
+            }
+
+            Ok(page)
+        })
+    }
+}
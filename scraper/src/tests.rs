@@ -40,7 +40,7 @@ const TEST_DATA: &[[&str; 3]] = &[
     [
         "FS: Heuer Autavia Valjoux Circa 1972 73663",
         "73663",
-        "Heuer",
+        "TAG Heuer",
     ],
     [
         "FS: 2023 126508 Rolex Daytona Yellow Gold \"Pikachu\" EXCELLENT CONDITON/COMPLETE SET",
@@ -62,6 +62,31 @@ const TEST_DATA: &[[&str; 3]] = &[
         "126300",
         "Rolex",
     ],
+    // abbreviations and collections name the brand
+    [
+        "AP Royal Oak 15500ST blue dial",
+        "15500st",
+        "Audemars Piguet",
+    ],
+    [
+        "JLC Reverso Tribute Duoface Q3988482",
+        "q3988482",
+        "Jaeger-LeCoultre",
+    ],
+    [
+        "PP 5711/1A-010 full set 2019",
+        "5711/1a-010",
+        "Patek Philippe",
+    ],
+    ["GS SBGA211 Snowflake", "", "Grand Seiko"],
+    [
+        "VC Overseas 4500V/110A-B128",
+        "4500v/110a-b128",
+        "Vacheron Constantin",
+    ],
+    ["UN Freak X 2303-270", "2303-270", "Ulysse Nardin"],
+    ["WTS Submariner 116610LN 2015", "116610ln", "Rolex"],
+    ["Nautilus 5711 Tiffany dial", "5711", "Patek Philippe"],
 ];
 
 #[test]
@@ -133,3 +158,1357 @@ fn currency() {
 
     //assert_eq!(c_usd1, 4232);
 }
+
+#[test]
+fn schedule_backoff() {
+    use crate::daemon::Schedule;
+    use std::time::Duration;
+
+    let schedule = Schedule {
+        interval: Duration::from_secs(600),
+        jitter: Duration::ZERO,
+        backoff: Duration::from_secs(10),
+        max_backoff: Duration::from_secs(60),
+    };
+
+    assert_eq!(schedule.next_delay(0), Duration::from_secs(600));
+    assert_eq!(schedule.next_delay(1), Duration::from_secs(10));
+    assert_eq!(schedule.next_delay(3), Duration::from_secs(40));
+    assert_eq!(schedule.next_delay(10), Duration::from_secs(60));
+}
+
+#[test]
+fn sources_config() {
+    use crate::config::{Config, SourceKind};
+
+    let config = Config::parse(include_str!("../../scraper.toml")).unwrap();
+    let names: Vec<_> = config.sources.iter().map(|s| s.database()).collect();
+
+    assert_eq!(names, ["RolexForums_9", "RolexForums_40"]);
+    assert!(matches!(
+        config.sources[0].kind,
+        SourceKind::RolexForums { forum_id: 9 }
+    ));
+    assert_eq!(
+        config.sources[0].schedule.schedule().interval.as_secs(),
+        900
+    );
+
+    let vbulletin = Config::parse(
+        r#"
+        [[source]]
+        kind = "vbulletin"
+        forum_id = 12
+
+        [source.site]
+        base_url = "https://forum.example.com"
+        sticky_rows = 2
+    "#,
+    )
+    .unwrap();
+    let (site, forum_id) = vbulletin.sources[0].kind.vbulletin().unwrap();
+
+    assert_eq!(vbulletin.sources[0].database(), "forum.example.com_12");
+    assert_eq!(forum_id, 12);
+    assert_eq!(site.sticky_rows, 2);
+    assert_eq!(site.title_id_prefix, "thread_title_");
+
+    let duplicate = r#"
+        [[source]]
+        kind = "rolex_forums"
+        forum_id = 9
+
+        [[source]]
+        kind = "rolex_forums"
+        forum_id = 9
+        enabled = false
+    "#;
+
+    assert!(Config::parse(duplicate).is_err());
+
+    // requests couldn't be spaced out at these rates
+    for rate in ["0", "-1", "inf", "nan"] {
+        let config = format!("[politeness]\nrequests_per_second = {rate}");
+
+        assert!(
+            matches!(Config::parse(&config), Err(WatchError::Config(_))),
+            "{rate}"
+        );
+    }
+}
+
+#[test]
+fn reference_catalog() {
+    use crate::catalog::Catalog;
+
+    let catalog = Catalog::parse(include_str!("../../catalog.toml")).unwrap();
+    let find = |title: &str| {
+        let tokens = tokenize_watch_info(title);
+        let brand = find_brand(&tokens)
+            .ok()
+            .or_else(|| catalog.brand_of(&tokens))
+            .unwrap();
+
+        find_reference(&tokens, brand, &catalog).unwrap()
+    };
+
+    // a catalog reference wins over a longer looking token
+    let (model_no, watch) = find("Rolex Submariner 116610LN box 2015 serial 12345678");
+
+    assert_eq!(&*model_no, "116610ln");
+    assert_eq!(watch.unwrap().info.discontinued, Some(2020));
+
+    // the bezel code is filled in when only one reference fits
+    let (model_no, watch) = find("Rolex Explorer II 216570 polar");
+
+    assert_eq!(&*model_no, "216570");
+    assert_eq!(watch.unwrap().info.case_size, Some(42.0));
+    assert_eq!(&*find("Rolex Daytona 116500 panda").0, "116500ln");
+    assert_eq!(&*find("Rolex Submariner 126610 LN").0, "126610ln");
+    assert_eq!(&*find("Rolex Submariner 126610-LN").0, "126610ln");
+    assert!(find("Rolex 126610 Submariner").1.is_none());
+
+    // the reference alone names the brand
+    let (model_no, watch) = find("FS: 79030N full set");
+
+    assert_eq!(&*model_no, "79030n");
+    assert_eq!(&*watch.unwrap().brand, "TUDOR");
+
+    // unknown references are still found, without model information
+    let (model_no, watch) = find("Rolex Datejust 16234");
+
+    assert_eq!(&*model_no, "16234");
+    assert!(watch.is_none());
+}
+
+#[test]
+fn brand_hierarchy() {
+    let brands = |title: &str| -> Vec<_> {
+        rank_brands(&tokenize_watch_info(title))
+            .into_iter()
+            .map(|c| c.value)
+            .collect()
+    };
+
+    // names of one brand resolve to it
+    assert_eq!(brands("Bovet Fleurier Amadeo"), ["Bovet"]);
+    assert_eq!(brands("Abraham-Louis Breguet Tradition 7057"), ["Breguet"]);
+    assert_eq!(brands("Breguet Marine 5517"), ["Breguet"]);
+    assert_eq!(brands("TAG Heuer Carrera CBN2010"), ["TAG Heuer"]);
+
+    // a sub-brand isn't its parent
+    assert_eq!(brands("Grand Seiko SBGA211"), ["Grand Seiko"]);
+    assert_eq!(brands("Orient Star RE-AU0005L"), ["ORIENT STAR"]);
+    assert_eq!(brands("Seiko SKX007"), ["Seiko"]);
+
+    // generic words don't name a brand
+    assert!(brands("Steel watch and co").is_empty());
+    assert_eq!(brands("Rolex watch 16610 steel"), ["Rolex"]);
+    assert_eq!(brands("TW Steel CE1001"), ["TW Steel"]);
+    assert_eq!(brands("Tiffany & Co dial Rolex 16234")[0], "Rolex");
+}
+
+#[test]
+fn collection() {
+    use crate::catalog::Catalog;
+
+    let catalog = Catalog::parse(include_str!("../../catalog.toml")).unwrap();
+    let collection = |title: &str| identify_watch(&tokenize_watch_info(title), &catalog).collection;
+
+    assert_eq!(collection("Omega speedmaster nib"), Some("Speedmaster"));
+    assert_eq!(
+        collection("AP Royal Oak Offshore 26470ST"),
+        Some("Royal Oak Offshore")
+    );
+    assert_eq!(
+        collection("Rolex GMT-Master II Pepsi"),
+        Some("GMT-Master II")
+    );
+    assert_eq!(collection("Tudor Black Bay 58 blue"), Some("Black Bay"));
+    assert_eq!(collection("Rolex 16234 box and papers"), None);
+
+    // the catalog knows the family of its references
+    assert_eq!(collection("Rolex 126710BLRO"), Some("GMT-Master II"));
+    assert_eq!(collection("FS: 5711/1A-010 full set"), Some("Nautilus"));
+
+    let entry = PriceEntry::from_title(1, 0, "Omega speedmaster nib").unwrap();
+
+    assert_eq!(entry.collection.as_deref(), Some("Speedmaster"));
+    assert_eq!(&*entry.model_no, "");
+    assert!(entry.needs_review);
+}
+
+#[test]
+fn watch_attributes() {
+    use crate::attributes::find_attributes;
+
+    let attributes = |title: &str| find_attributes(&tokenize_watch_info(title));
+
+    let a = attributes(
+        "FS: IWC Portuguese Yacht Club Chronograph Certified Steel Black 45mm IW390204 Rubber",
+    );
+
+    assert_eq!(a.case_size, Some(45.0));
+    assert_eq!(a.material.as_deref(), Some("steel"));
+    assert_eq!(a.bracelet.as_deref(), Some("rubber"));
+    assert_eq!(a.dial, None);
+    assert_eq!(a.complications, ["chronograph".into()]);
+
+    let a = attributes("Rolex GMT-Master II 126715CHNR Everose 40 mm black dial Oysterflex");
+
+    assert_eq!(a.case_size, Some(40.0));
+    assert_eq!(a.material.as_deref(), Some("rose gold"));
+    assert_eq!(a.bracelet.as_deref(), Some("rubber"));
+    assert_eq!(a.dial.as_deref(), Some("black"));
+    assert_eq!(a.complications, ["gmt".into()]);
+
+    let a = attributes("Omega Seamaster 38.5mm 18k yellow gold ice blue dial moonphase chrono");
+
+    assert_eq!(a.case_size, Some(38.5));
+    assert_eq!(a.material.as_deref(), Some("yellow gold"));
+    assert_eq!(a.dial.as_deref(), Some("ice blue"));
+    assert_eq!(a.complications, ["chronograph".into(), "moon phase".into()]);
+
+    // a color elsewhere isn't the dial's
+    assert_eq!(
+        attributes("Rolex 126710BLRO blue red bezel jubilee").dial,
+        None
+    );
+    assert!(attributes("Rolex 16610 box and papers").is_empty());
+}
+
+#[test]
+fn listing_kind() {
+    use crate::{
+        cli::ExportFilter,
+        listing_kind::{classify_listing, ListingKind},
+    };
+
+    let cases = [
+        ("FS: Rolex Submariner 116610LN", ListingKind::ForSale),
+        (
+            "[WTS] Omega Speedmaster 311.30.42.30.01.005",
+            ListingKind::ForSale,
+        ),
+        ("FSOT: Tudor Black Bay 79230B", ListingKind::ForSaleOrTrade),
+        ("FS/T Heuer Autavia 73663", ListingKind::ForSaleOrTrade),
+        ("[WTS/WTT] Grand Seiko SBGA211", ListingKind::ForSaleOrTrade),
+        (
+            "Rolex 16610 - for sale or trade",
+            ListingKind::ForSaleOrTrade,
+        ),
+        ("WTB: Rolex Explorer 214270", ListingKind::WantToBuy),
+        ("[WTB/WTT] Patek 5711", ListingKind::WantToBuy),
+        ("Looking for a Daytona 116500LN", ListingKind::WantToBuy),
+        ("WTT my AP 15400 for a Nautilus", ListingKind::WantToTrade),
+        ("PC: Rolex 1675 GMT", ListingKind::Other),
+        ("[Worth?] Rolex 16233 full set", ListingKind::Other),
+        ("Wanted: Omega 145.022", ListingKind::WantToBuy),
+        ("LF Tudor 79230R", ListingKind::WantToBuy),
+        ("(FT) Seiko SLA017 for a diver", ListingKind::WantToTrade),
+        ("Meta: Rolex 126610LN fakes", ListingKind::Other),
+        ("Rolex 126710BLRO full set 2021", ListingKind::ForSale),
+        // the same words in the title don't make a tag
+        ("Rolex 16610 well worth it $8000", ListingKind::ForSale),
+        ("The most wanted Daytona 116500LN", ListingKind::ForSale),
+        ("Rolex 5513, any question welcome", ListingKind::ForSale),
+        (
+            "Omega 2254.50 with 20 ft of papers, meta box",
+            ListingKind::ForSale,
+        ),
+    ];
+
+    for (title, kind) in cases {
+        assert_eq!(classify_listing(title), kind, "{title}");
+    }
+
+    assert!(ListingKind::ForSaleOrTrade.is_sale());
+    assert!(!ListingKind::WantToBuy.is_sale());
+
+    // the fsot marker is kept for the kind but isn't read as a reference
+    let entry = PriceEntry::from_title(1, 0, "FSOT: Rolex Datejust 16234").unwrap();
+
+    assert_eq!(entry.kind, Some(ListingKind::ForSaleOrTrade));
+    assert_eq!(&*entry.model_no, "16234");
+
+    // entries stored without a kind stay without one when saved again
+    let legacy = serde_json::json!({
+        "id": 1, "timestamp": 0, "price": 100, "is_sold": false,
+        "brand": "Rolex", "model_no": "16610"
+    });
+    let entry: PriceEntry = serde_json::from_value(legacy.clone()).unwrap();
+
+    assert_eq!(entry.kind, None);
+    assert!(serde_json::to_value(&entry).unwrap().get("kind").is_none());
+
+    // and are only exported when asked for, like other kinds
+    let wanted = serde_json::json!({ "kind": "want_to_buy" });
+    let sales = ExportFilter {
+        kinds: Some(vec![ListingKind::ForSale, ListingKind::ForSaleOrTrade]),
+        unclassified: false,
+        review: false,
+    };
+
+    assert!(sales.keeps(&serde_json::json!({ "kind": "for_sale" })));
+    assert!(!sales.keeps(&wanted));
+    assert!(!sales.keeps(&legacy));
+    assert!(ExportFilter {
+        unclassified: true,
+        ..sales
+    }
+    .keeps(&legacy));
+    assert!(ExportFilter {
+        kinds: None,
+        unclassified: true,
+        review: false,
+    }
+    .keeps(&wanted));
+}
+
+#[test]
+fn identification_confidence() {
+    use crate::catalog::Catalog;
+
+    let catalog = Catalog::parse(include_str!("../../catalog.toml")).unwrap();
+    let identify = |title: &str| identify_watch(&tokenize_watch_info(title), &catalog);
+
+    // a catalog reference is certain, the other tokens are ranked after it
+    let id = identify("Rolex Submariner 116610LN box 2015 serial 12345678");
+
+    assert_eq!(id.brand(), Some("Rolex"));
+    assert_eq!(&*id.references[0].value.model_no, "116610ln");
+    assert_eq!(id.references[0].confidence, 1.0);
+    assert_eq!(&*id.references[1].value.model_no, "12345678");
+    assert_eq!(id.confidence(), 1.0);
+    assert!(!id.needs_review());
+
+    // a reference that only looks like one is less sure
+    let id = identify("Rolex Datejust 16234");
+
+    assert_eq!(id.confidence(), 0.6);
+    assert!(!id.needs_review());
+
+    // "UN" is as good a match as Rolex, neither brand can be trusted
+    let id = identify("UN Rolex 16234 Freak");
+
+    assert_eq!(id.brands[0].confidence, 0.5);
+    assert_eq!(id.brands[1].confidence, 0.5);
+    assert!(id.needs_review());
+
+    // a collection alone names the brand less surely
+    let id = identify("Speedmaster 3570.50 full set");
+
+    assert_eq!(id.brand(), Some("Omega"));
+    assert!(id.needs_review());
+
+    // no reference at all
+    let id = identify("Rolex box and papers");
+
+    assert_eq!(id.brand(), Some("Rolex"));
+    assert!(id.references.is_empty());
+    assert_eq!(id.confidence(), 0.0);
+    assert!(id.needs_review());
+    assert!(identify("Looking for a leather strap").brands.is_empty());
+}
+
+#[test]
+fn robots_txt() {
+    use crate::http::Robots;
+
+    let robots = Robots::parse(
+        "User-agent: *\nDisallow: /private/\nAllow: /private/open\nDisallow: /*.php$\n\nUser-agent: OtherBot\nDisallow: /",
+        "watchinspect",
+    );
+
+    assert!(robots.allowed("/forumdisplay"));
+    assert!(!robots.allowed("/private/x"));
+    assert!(robots.allowed("/private/open/x"));
+    assert!(!robots.allowed("/showthread.php"));
+    assert!(robots.allowed("/showthread.php5"));
+
+    let robots = Robots::parse("User-agent: *\nDisallow: /search.php?do=", "watchinspect");
+
+    assert!(!robots.allowed("/search.php?do=getnew"));
+    assert!(robots.allowed("/search.php?searchid=5"));
+
+    // a group for our own agent replaces the catch-all group
+    let robots = Robots::parse(
+        "User-agent: *\nDisallow: /\n\nUser-agent: watchinspect\nDisallow: /admin",
+        "watchinspect/1.0",
+    );
+
+    assert!(robots.allowed("/forumdisplay"));
+    assert!(!robots.allowed("/admin"));
+
+    // a group naming us that allows everything still replaces the catch-all
+    let robots = Robots::parse(
+        "User-agent: *\nDisallow: /\n\nUser-agent: watchinspect\nDisallow:",
+        "watchinspect",
+    );
+
+    assert!(robots.allowed("/forumdisplay"));
+}
+
+#[tokio::test]
+async fn record_replay() {
+    use crate::fetch::{Fetcher, RecordingFetcher, ReplayFetcher};
+    use futures::future::BoxFuture;
+    use std::sync::Arc;
+
+    struct Stub;
+
+    impl Fetcher for Stub {
+        fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+            Box::pin(async move { Ok(format!("<html>{url}</html>")) })
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("watchinspect-corpus-{}", std::process::id()));
+    let url = "https://www.rolexforums.com/forumdisplay.php?f=9&order=desc&page=1";
+
+    let recorder = RecordingFetcher::new(Arc::new(Stub), &dir);
+    let recorded = recorder.fetch(url).await.unwrap();
+
+    let replay = ReplayFetcher::new(&dir);
+
+    assert_eq!(replay.fetch(url).await.unwrap(), recorded);
+    assert!(matches!(
+        replay.fetch("https://www.rolexforums.com/other").await,
+        Err(WatchError::NotRecorded(_))
+    ));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn db_backup_recovery() {
+    use crate::paths;
+
+    let dir = std::env::temp_dir().join(format!("watchinspect-data-{}", std::process::id()));
+
+    let name = "BackupTest";
+    let mut db = PriceDatabase::<u32>::new(name);
+
+    for i in 0..5 {
+        db.entries.push(i);
+        db.save_in(&dir).await.unwrap();
+    }
+
+    // only the configured number of previous generations is kept
+    assert!(paths::db_backup_path(&dir, name, DB_BACKUP_GENERATIONS).exists());
+    assert!(!paths::db_backup_path(&dir, name, DB_BACKUP_GENERATIONS + 1).exists());
+
+    // a database truncated mid-write falls back to the newest backup
+    std::fs::write(paths::db_path(&dir, name), "{\"name\":\"Backu").unwrap();
+
+    let mut db = PriceDatabase::<u32>::new(name);
+
+    db.load_in(&dir).await.unwrap();
+
+    assert_eq!(db.entries, [0, 1, 2, 3]);
+    assert!(paths::db_path(&dir, name)
+        .with_extension("json.corrupt")
+        .exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+const ROLEX_FORUMS_PAGE: &str = r#"<html><body><form id="inlinemodform">
+<table><tr><td class="vbmenu_control">Page 2 of 57</td></tr></table>
+<table><tbody id="threadbits_forum_9">
+<tr>
+    <td class="alt1"><a id="thread_title_123" href="showthread.php?t=123">FS: Rolex 126300 Datejust 41 Blue</a></td>
+    <td class="alt2">replies</td>
+    <td class="alt2"><div class="smallfont">21 July 2023 <span class="time">04:52 PM</span></div></td>
+</tr>
+</tbody></table>
+</form></body></html>"#;
+
+#[test]
+fn vbulletin_page() {
+    let rolex_forums = VBulletinSite::new(VBulletinConfig::rolex_forums(), 9).unwrap();
+    let (entries, max_page) = rolex_forums.parse_page(ROLEX_FORUMS_PAGE, 2).unwrap();
+
+    assert_eq!(max_page, 57);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].id, 123);
+    assert_eq!(&*entries[0].brand, "Rolex");
+    assert_eq!(&*entries[0].model_no, "126300");
+
+    // a login wall or challenge page names the selector that failed
+    let login_wall = "<html><body><form action=\"login.php\"></form></body></html>";
+
+    assert!(matches!(
+        rolex_forums.parse_page(login_wall, 2),
+        Err(WatchError::MissingElement(selector)) if selector == "#inlinemodform"
+    ));
+
+    let bad_id = ROLEX_FORUMS_PAGE.replace("thread_title_123", "thread_title_x");
+
+    assert!(matches!(
+        rolex_forums.parse_page(&bad_id, 2),
+        Err(WatchError::ParseField {
+            field: "thread id",
+            ..
+        })
+    ));
+
+    // another theme: US dates, sticky threads marked by an icon
+    let site = VBulletinSite::new(
+        VBulletinConfig {
+            base_url: "https://forum.example.com".to_owned(),
+            date_formats: vec!["%m-%d-%Y %I:%M %p".to_owned()],
+            sticky_selector: Some("img.sticky".to_owned()),
+            ..Default::default()
+        },
+        12,
+    )
+    .unwrap();
+    let sticky_row = r#"<tbody id="threadbits_forum_9">
+<tr><td class="alt1"><img class="sticky"><a id="thread_title_1">Rules</a></td></tr>"#;
+    let page = ROLEX_FORUMS_PAGE
+        .replace(r#"<tbody id="threadbits_forum_9">"#, sticky_row)
+        .replace("21 July 2023", "07-21-2023");
+    let (entries, _) = site.parse_page(&page, 1).unwrap();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].timestamp, 1689958320);
+    assert_eq!(
+        site.thread_url(entries[0].id),
+        "https://forum.example.com/showthread.php?t=123"
+    );
+    assert!(VBulletinSite::new(VBulletinConfig::default(), 12).is_err());
+}
+
+#[tokio::test]
+async fn forum_concurrent_threads() {
+    use crate::{
+        fetch::{corpus_file_name, Fetcher, ReplayFetcher},
+        scrapers::forum::{Forum, ForumSite},
+    };
+    use std::sync::Arc;
+
+    let site = Arc::new(VBulletinSite::new(VBulletinConfig::rolex_forums(), 9).unwrap());
+    let dir = std::env::temp_dir().join(format!("watchinspect-threads-{}", std::process::id()));
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // thread 13 was never recorded, its entry is merged without a price
+    for (id, price) in [(11, "$9,500"), (12, "$12,000"), (14, "$7,250")] {
+        let page = format!(
+            "<html><body><div id=\"post_message_{id}\">Asking {price} shipped</div></body></html>"
+        );
+
+        std::fs::write(dir.join(corpus_file_name(&site.thread_url(id))), page).unwrap();
+    }
+
+    let fetcher: Arc<dyn Fetcher> = Arc::new(ReplayFetcher::new(&dir));
+    let entry = |id, timestamp| {
+        PriceEntry::from_title(id, timestamp, "FS: Rolex 126300 Datejust 41").unwrap()
+    };
+
+    let mut data = PriceDatabase::<PriceEntry>::new("ConcurrentThreads");
+    data.entries = vec![entry(12, 0), entry(15, 0)];
+
+    let page = vec![
+        entry(14, 1),
+        entry(15, 0),
+        entry(11, 1),
+        entry(13, 1),
+        entry(12, 1),
+    ];
+    let unchanged = Forum::merge_entries(&mut data, &site, &fetcher, page, 4).await;
+
+    assert_eq!(unchanged, 1);
+
+    // existing entries are replaced in place, new ones follow in list order
+    let ids: Vec<_> = data.entries.iter().map(|e| e.id).collect();
+    let prices: Vec<_> = data.entries.iter().map(|e| e.price).collect();
+
+    assert_eq!(ids, [12, 15, 14, 11, 13]);
+    assert_eq!(
+        prices,
+        [Some(1_200_000), None, Some(725_000), Some(950_000), None]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+const XENFORO_PAGE: &str = r#"<html><body>
+<div class="structItemContainer-group structItemContainer-group--sticky">
+<div class="structItem structItem--thread js-threadListItem-1">
+    <div class="structItem-title"><a href="/threads/rules.1/" data-tp-primary="on">Rolex 116500 rules</a></div>
+    <time class="structItem-latestDate u-dt" data-time="1600000000">Sep 13, 2020</time>
+</div>
+</div>
+<div class="structItemContainer-group js-threadList">
+<div class="structItem structItem--thread js-inlineModContainer js-threadListItem-4521">
+    <div class="structItem-title">
+        <a href="/forums/sales.3/?prefix_id=2" class="labelLink"><span class="label label--red">Sold</span></a>
+        <a href="/threads/rolex-126300.4521/" data-tp-primary="on">Rolex Datejust 126300 blue</a>
+    </div>
+    <time class="structItem-latestDate u-dt" data-time="1689958320">Jul 21, 2023</time>
+</div>
+<div class="structItem structItem--thread js-threadListItem-4522">
+    <div class="structItem-title">
+        <span class="label label--orange">Pending</span>
+        <a href="/threads/omega.4522/" data-tp-primary="on">Omega Speedmaster 310.30.42.50.01.001</a>
+    </div>
+    <time class="structItem-latestDate u-dt" data-time="1689958000">Jul 21, 2023</time>
+</div>
+</div>
+<ul class="pageNav-main"><li class="pageNav-page"><a href="/forums/sales.3/">1</a></li>
+<li class="pageNav-page"><a href="/forums/sales.3/page-12">12</a></li></ul>
+</body></html>"#;
+
+#[test]
+fn xenforo_page() {
+    use crate::listing_kind::ListingKind;
+
+    let site = XenForoSite::new(
+        XenForoConfig {
+            base_url: "https://forum.example.com".to_owned(),
+            ..Default::default()
+        },
+        "sales.3",
+    )
+    .unwrap();
+
+    assert_eq!(
+        site.list_url(2),
+        "https://forum.example.com/forums/sales.3/page-2?order=last_post_date&direction=desc"
+    );
+
+    let (entries, max_page) = site.parse_page(XENFORO_PAGE, 1).unwrap();
+
+    // the sticky thread is skipped
+    assert_eq!(max_page, 12);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].id, 4521);
+    assert_eq!(entries[0].timestamp, 1689958320);
+    assert_eq!(&*entries[0].model_no, "126300");
+    assert!(entries[0].is_sold);
+    assert!(!entries[1].is_sold && entries[1].is_pending);
+    assert_eq!(entries[1].kind, Some(ListingKind::ForSale));
+
+    // only the first post is the listing
+    let mut entry = entries[1].clone();
+    let thread = r#"<article class="message message--post"><div class="bbWrapper">Full set, no trades</div></article>
+<article class="message message--post"><div class="bbWrapper">Mine sold last week</div></article>"#;
+
+    site.read_thread(thread, &mut entry);
+
+    assert!(!entry.is_sold);
+
+    site.read_thread(&thread.replace("no trades", "sold, thanks"), &mut entry);
+
+    assert!(entry.is_sold);
+
+    // the prefix says what the title doesn't
+    let wanted = XENFORO_PAGE.replace(">Pending<", ">Wanted<");
+    let (entries, _) = site.parse_page(&wanted, 1).unwrap();
+
+    assert_eq!(entries[1].kind, Some(ListingKind::WantToBuy));
+    assert!(!entries[1].is_pending);
+}
+
+const SELECTOR_PAGE: &str = r#"<html><body><ul>
+<li class="listing featured"><h3><a href="/listing/1">Advertise here</a></h3></li>
+<li class="listing"><h3><a href="/listing/8812">Rolex 126610LN Submariner</a></h3>
+    <span class="badge-sold">Sold</span><time datetime="2023-07-21T16:52:00">2 hours ago</time></li>
+<li class="listing"><h3><a href="/listing/8813">Omega 310.30.42.50.01.001</a></h3>
+    <time datetime="2023-07-21T15:00:00">4 hours ago</time></li>
+</ul><ol class="pagination"><li>1</li><li>2</li><li>14</li></ol></body></html>"#;
+
+const SELECTOR_CONFIG: &str = r#"
+list_url = "https://market.example.com/watches?page={page}"
+thread_url = "https://market.example.com/listing/{id}"
+row_selector = "li.listing"
+title = "h3 a"
+id = { selector = "h3 a", attr = "href", pattern = "/listing/(\\d+)" }
+date = { selector = "time", attr = "datetime" }
+date_formats = ["%Y-%m-%dT%H:%M:%S"]
+page_count = ".pagination li:last-child"
+skip_selector = "li.featured"
+sold_selector = ".badge-sold"
+post_selector = ".listing-description"
+"#;
+
+#[test]
+fn selector_page() {
+    use crate::fetch::stable_hash;
+
+    let config: SelectorConfig = toml::from_str(SELECTOR_CONFIG).unwrap();
+    let site = SelectorSite::new(config).unwrap();
+    let (entries, max_page) = site.parse_page(SELECTOR_PAGE, 1).unwrap();
+
+    assert_eq!(max_page, 14);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].id, 8812);
+    assert_eq!(entries[0].timestamp, 1689958320);
+    assert_eq!(&*entries[0].model_no, "126610ln");
+    assert!(entries[0].is_sold);
+    assert!(!entries[1].is_sold);
+    assert_eq!(
+        site.thread_url(8812),
+        "https://market.example.com/listing/8812"
+    );
+
+    // a promo row without an id or date is left out
+    let promo = SELECTOR_PAGE.replace(
+        "</ul>",
+        r#"<li class="listing"><h3><a href="/sell">Sell your Rolex 126610LN</a></h3></li></ul>"#,
+    );
+
+    assert_eq!(site.parse_page(&promo, 1).unwrap().0.len(), 2);
+
+    // listings known by a slug get an id of their own
+    let config: SelectorConfig =
+        toml::from_str(&SELECTOR_CONFIG.replace(r"(\\d+)", r"([\\w-]+)")).unwrap();
+    let slugs = SelectorSite::new(config).unwrap();
+    let page = SELECTOR_PAGE.replace("/listing/8812", "/listing/rolex-submariner-8812");
+    let (entries, _) = slugs.parse_page(&page, 1).unwrap();
+
+    assert_eq!(entries[0].id, stable_hash("rolex-submariner-8812"));
+    assert_eq!(entries[1].id, 8813);
+    assert_eq!(
+        slugs.thread_url(entries[0].id),
+        "https://market.example.com/listing/rolex-submariner-8812"
+    );
+
+    // a changed link format names the field
+    let moved = SELECTOR_PAGE.replace("/listing/", "/item/");
+
+    assert!(matches!(
+        site.parse_page(&moved, 1),
+        Err(WatchError::ParseField {
+            field: "thread id",
+            ..
+        })
+    ));
+    assert!(site.parse_page("<html></html>", 1).is_err());
+}
+
+const REDDIT_LISTING: &str = r#"{"kind": "Listing", "data": {"after": "t3_15abc", "children": [
+    {"kind": "t3", "data": {"id": "15abz", "title": "[WTS] Rolex 126300 Datejust 41 blue, full set",
+        "selftext": "Box and papers\n\nNo trades", "link_flair_text": "Sold", "created_utc": 1689958320.0}},
+    {"kind": "t3", "data": {"id": "15abc", "title": "[WTS/WTT] Omega Speedmaster 310.30.42.50.01.001",
+        "selftext": "", "link_flair_text": null, "created_utc": 1689958000.0}},
+    {"kind": "t3", "data": {"id": "15aca", "title": "[WTB] Rolex 126300",
+        "selftext": "", "link_flair_text": null, "created_utc": 1689957000.0}},
+    {"kind": "t3", "data": {"id": "15acb", "title": "[META] Rolex 126300 scams",
+        "selftext": "", "created_utc": 1689956000.0}}
+]}}"#;
+
+#[test]
+fn reddit_listing() {
+    let config = RedditConfig::default();
+    let (entries, after) = RedditListing::parse_listing(&config, REDDIT_LISTING).unwrap();
+
+    // buyers and meta posts are left out
+    assert_eq!(entries.len(), 2);
+    assert_eq!(after.as_deref(), Some("t3_15abc"));
+    assert_eq!(entries[0].id, u64::from_str_radix("15abz", 36).unwrap());
+    assert_eq!(entries[0].timestamp, 1689958320);
+    assert_eq!(&*entries[0].model_no, "126300");
+    assert!(entries[0].is_sold);
+    assert!(!entries[1].is_sold);
+
+    assert_eq!(
+        RedditListing::listing_url(&config, "Watchexchange", after.as_deref()),
+        "https://www.reddit.com/r/Watchexchange/new.json?limit=100&raw_json=1&after=t3_15abc"
+    );
+}
+
+const PRODUCT_PAGE: &str = r#"<html><head>
+<script type="application/ld+json">
+{"@context": "https://schema.org", "@graph": [
+    {"@type": "BreadcrumbList", "itemListElement": []},
+    {"@type": "Product", "name": "Oyster Perpetual Submariner Date", "brand": {"@type": "Brand", "name": "Rolex"},
+        "sku": "STK-8812", "mpn": "126610LN",
+        "offers": {"@type": "Offer", "price": 14950, "priceCurrency": "USD",
+            "availability": "https://schema.org/SoldOut", "url": "https://dealer.example.com/w/8812"}}
+]}
+</script>
+<script type="application/ld+json">{ not json</script>
+</head></html>"#;
+
+#[test]
+fn product_offers() {
+    let offers = page_offers(PRODUCT_PAGE);
+
+    assert_eq!(
+        offers,
+        [Offer {
+            name: "Oyster Perpetual Submariner Date".to_owned(),
+            brand: Some("Rolex".to_owned()),
+            sku: Some("STK-8812".to_owned()),
+            mpn: Some("126610LN".to_owned()),
+            url: Some("https://dealer.example.com/w/8812".to_owned()),
+            price: Some("14950".to_owned()),
+            currency: Some("USD".to_owned()),
+            is_sold: true,
+        }]
+    );
+
+    // the mpn is the reference, the dealer's sku isn't
+    let entry = Offer {
+        price: None,
+        ..offers[0].clone()
+    }
+    .to_entry("https://dealer.example.com/list", 0)
+    .unwrap();
+
+    assert_eq!(&*entry.brand, "Rolex");
+    assert_eq!(&*entry.model_no, "126610ln");
+    assert_eq!(entry.collection.as_deref(), Some("Submariner"));
+    assert!(!entry.needs_review);
+
+    // any currency with rates converts, Singapore dealers price in SGD
+    let entry = Offer {
+        price: Some("20000".to_owned()),
+        currency: Some("SGD".to_owned()),
+        ..offers[0].clone()
+    }
+    .to_entry("https://dealer.example.com/list", 1689958320)
+    .unwrap();
+
+    assert!(entry.price.is_some_and(|p| p > 1300000 && p < 1600000));
+
+    // an offer without a reference is kept for review
+    let entry = Offer {
+        name: "Rolex Submariner, full set".to_owned(),
+        mpn: None,
+        sku: None,
+        ..offers[0].clone()
+    }
+    .to_entry("https://dealer.example.com/list", 0)
+    .unwrap();
+
+    assert_eq!(&*entry.model_no, "");
+    assert!(entry.needs_review);
+
+    // microdata is used when a page has no JSON-LD
+    let microdata = r#"<div itemscope itemtype="https://schema.org/Product">
+    <h1 itemprop="name">Omega Speedmaster Professional</h1>
+    <span itemprop="brand" itemscope itemtype="https://schema.org/Brand"><span itemprop="name">Omega</span></span>
+    <meta itemprop="mpn" content="310.30.42.50.01.001">
+    <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+        <span itemprop="price" content="6100.00">6,100</span><meta itemprop="priceCurrency" content="EUR">
+        <link itemprop="availability" href="https://schema.org/InStock">
+    </div>
+</div>"#;
+    let offers = page_offers(microdata);
+
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0].brand.as_deref(), Some("Omega"));
+    assert_eq!(offers[0].mpn.as_deref(), Some("310.30.42.50.01.001"));
+    assert_eq!(offers[0].price.as_deref(), Some("6100.00"));
+    assert_eq!(offers[0].currency.as_deref(), Some("EUR"));
+    assert!(!offers[0].is_sold);
+}
+
+const RSS_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0"><channel><title>Watches for sale</title>
+    <item><title>Rolex 126610LN Submariner</title><link>https://forum.example.com/t/8812</link>
+        <guid isPermaLink="false">thread-8812</guid><pubDate>Fri, 21 Jul 2023 16:52:00 GMT</pubDate>
+        <description>&lt;p&gt;Full set&lt;/p&gt;&lt;p&gt;Asking $12,500 shipped&lt;/p&gt;</description></item>
+    <item><title>Looking for a strap</title><link>https://forum.example.com/t/8813</link></item>
+</channel></rss>"#;
+
+const ATOM_FEED: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>Sales corner</title>
+    <entry><title>Omega Speedmaster 310.30.42.50.01.001 - SOLD</title>
+        <id>tag:market.example.com,2023:post-51</id><link href="https://market.example.com/threads/51/"/>
+        <updated>2023-07-21T16:52:00+02:00</updated><summary type="html">Was $6,100</summary></entry>
+</feed>"#;
+
+#[test]
+fn feed() {
+    let items = parse_feed(RSS_FEED).unwrap();
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].guid, "thread-8812");
+    assert_eq!(items[0].published, Some(1689958320));
+    // items without a guid are known by their link
+    assert_eq!(items[1].guid, "https://forum.example.com/t/8813");
+
+    let entry = items[0].to_entry(0).unwrap();
+
+    assert_eq!(&*entry.brand, "Rolex");
+    assert_eq!(&*entry.model_no, "126610ln");
+    assert_eq!(entry.price, Some(1250000));
+
+    let atom = parse_feed(ATOM_FEED).unwrap();
+
+    assert_eq!(atom[0].guid, "tag:market.example.com,2023:post-51");
+    assert_eq!(atom[0].published, Some(1689951120));
+    assert!(atom[0].to_entry(0).unwrap().is_sold);
+
+    // items are only read once, identifiable or not
+    let mut db = PriceDatabase::new("feed");
+
+    assert_eq!(Feed::merge_items(&mut db, &items), 1);
+    assert_eq!(Feed::merge_items(&mut db, &items), 0);
+    assert_eq!(db.seen.len(), 2);
+    assert_eq!(db.entries.len(), 1);
+
+    // the oldest guids are forgotten, the file keeps them in order
+    for i in 0..SEEN_CAPACITY {
+        db.seen.insert(&i.to_string());
+    }
+
+    assert_eq!(db.seen.len(), SEEN_CAPACITY);
+    assert!(!db.seen.contains("thread-8812"));
+    assert!(db.seen.contains("0"));
+
+    let seen: Vec<String> =
+        serde_json::from_value(serde_json::to_value(&db.seen).unwrap()).unwrap();
+
+    assert_eq!(seen.first().map(String::as_str), Some("0"));
+
+    assert!(parse_feed("<html><body></body></html>").is_err());
+}
+
+#[test]
+fn archive() {
+    use crate::{
+        archive::{read_har, read_warc, Route},
+        config::SourceKind,
+    };
+    use base64::Engine;
+
+    let record = |url: &str, date: &str, page: &str| {
+        let http = format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n{page}");
+
+        format!(
+            "WARC/1.0\r\nWARC-Type: response\r\nWARC-Target-URI: {url}\r\nWARC-Date: {date}\r\nContent-Length: {}\r\n\r\n{http}\r\n\r\n",
+            http.len()
+        )
+    };
+    let list = ROLEX_FORUMS_PAGE.replace("21 July 2023", "Yesterday");
+    let thread = r#"<div id="post_message_1">Asking $9,800</div><div id="post_message_2">Sold, thanks</div>"#;
+    let warc = [
+        record(
+            "https://www.rolexforums.com/showthread.php?t=123",
+            "2015-03-02T13:00:00Z",
+            thread,
+        ),
+        record(
+            "http://www.rolexforums.com/forumdisplay.php?f=9&page=2",
+            "2015-03-02T12:00:00Z",
+            &list,
+        ),
+        record(
+            "http://www.rolexforums.com/forumdisplay.php?f=9&page=3",
+            "02.03.2015",
+            &list,
+        ),
+        // another sub-forum
+        record(
+            "https://www.rolexforums.com/forumdisplay.php?f=40&order=desc&page=2",
+            "2015-03-02T12:00:00Z",
+            &list,
+        ),
+    ]
+    .concat();
+
+    let records: Vec<_> = read_warc(std::io::Cursor::new(warc))
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+
+    assert_eq!(records.len(), 4);
+
+    let route = Route::new(&SourceKind::RolexForums { forum_id: 9 }).unwrap();
+    let mut db = PriceDatabase::new("archive");
+    let report = route.ingest(records, &mut db);
+
+    // the broken record is skipped
+    assert_eq!(report.pages, 2);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.added, 1);
+    // "Yesterday" is the day before the capture
+    assert_eq!(db.entries[0].timestamp, 1425228720);
+    assert_eq!(db.entries[0].price, Some(980000));
+    assert!(db.entries[0].is_sold);
+
+    let site = XenForoSite::new(
+        XenForoConfig {
+            base_url: "https://market.example.com".to_owned(),
+            ..Default::default()
+        },
+        "sales.8",
+    )
+    .unwrap();
+
+    assert_eq!(
+        site.page_of("https://market.example.com/threads/rolex-126300.51/"),
+        Some(ForumPage::Thread(51))
+    );
+    assert_eq!(
+        site.page_of("https://market.example.com/forums/sales.8/"),
+        Some(ForumPage::List(1))
+    );
+    assert_eq!(
+        site.page_of("https://market.example.com/forums/other.9/"),
+        None
+    );
+
+    let har = format!(
+        r#"{{"log": {{"entries": [
+            {{"startedDateTime": "2023-07-21T17:00:00.000Z",
+              "request": {{"url": "https://www.reddit.com/r/Watchexchange/new.json?limit=100"}},
+              "response": {{"status": 200, "content": {{"text": "{}", "encoding": "base64"}}}}}},
+            {{"startedDateTime": "2023-07-21T17:00:01.000Z",
+              "request": {{"url": "https://www.reddit.com/r/Watchexchange/new.json?after=t3_15abc"}},
+              "response": {{"status": 302, "content": {{}}}}}}
+        ]}}}}"#,
+        base64::engine::general_purpose::STANDARD.encode(REDDIT_LISTING)
+    );
+    let records = read_har(&har).unwrap();
+
+    assert_eq!(records.len(), 1);
+
+    let route = Route::new(&SourceKind::Reddit {
+        subreddit: "watchexchange".to_owned(),
+        dumps: None,
+        site: RedditConfig::default(),
+    })
+    .unwrap();
+    let mut db = PriceDatabase::new("archive");
+
+    assert_eq!(route.ingest(records, &mut db).added, 2);
+}
+
+#[test]
+fn csv_import() {
+    use crate::{
+        cli::ExportFilter,
+        import::{import_csv, ImportOptions},
+        listing_kind::ListingKind,
+    };
+
+    let csv = "Sale Date,Lot,Hammer,Currency,Auction House
+2023-05-13,Rolex Datejust 126300 blue dial,\"12,500\",USD,Phillips
+13/05/2023,Rolex Datejust 126300,12000,USD,Phillips
+2023-05-14,Unsigned pocket watch,300,USD,Local
+2023-05-13,Rolex Datejust 126300 blue dial,\"12,500\",USD,Phillips
+";
+    let mut data = PriceDatabase::new("ImportTest");
+    let report = import_csv(csv.as_bytes(), &ImportOptions::default(), &mut data).unwrap();
+
+    assert_eq!(report.imported, 1);
+    assert_eq!(report.duplicates, 1);
+    assert_eq!(
+        report
+            .errors
+            .iter()
+            .map(|(line, _)| *line)
+            .collect::<Vec<_>>(),
+        [3, 4]
+    );
+    assert!(matches!(
+        report.errors[0].1,
+        WatchError::ParseField { field: "date", .. }
+    ));
+
+    let entry = &data.entries[0];
+
+    assert_eq!(entry.timestamp, 1683936000);
+    // prices are stored in cents
+    assert_eq!(entry.price, Some(1250000));
+    assert!(entry.is_sold);
+    assert_eq!(&*entry.model_no, "126300");
+    assert_eq!(entry.origin.as_deref(), Some("Phillips"));
+
+    // explicit columns, brand and reference in their own columns
+    let csv =
+        "When,Make,Reference,Result,Sold?\n05/13/2023,Omega,311.30.42.30.01.005,EUR 6100,no\n";
+    let options = ImportOptions {
+        columns: vec![
+            ("date".to_owned(), "When".to_owned()),
+            ("price".to_owned(), "Result".to_owned()),
+            ("sold".to_owned(), "Sold?".to_owned()),
+        ],
+        date_format: Some("%m/%d/%Y".to_owned()),
+        currency: None,
+    };
+    let mut data = PriceDatabase::new("ImportTest");
+    let report = import_csv(csv.as_bytes(), &options, &mut data).unwrap();
+
+    assert_eq!(report.imported, 1, "{:?}", report.errors);
+    assert_eq!(&*data.entries[0].brand, "Omega");
+    assert!(!data.entries[0].is_sold);
+    assert!(data.entries[0].price.is_some_and(|p| p > 610000));
+
+    // decimal commas, and amounts and dates that can't be read surely
+    let csv = "Date,Title,Price,Currency
+2023-05-13,Omega Speedmaster 311.30.42.30.01.005,\"6.100,50\",EUR
+2023,Omega Speedmaster 311.30.42.30.01.005,6100,EUR
+2023-05-13,Omega Speedmaster 311.30.42.30.01.005,\"6.10,0\",EUR
+";
+    let mut data = PriceDatabase::new("ImportTest");
+    let report = import_csv(csv.as_bytes(), &ImportOptions::default(), &mut data).unwrap();
+    let price = data.entries[0].price.unwrap();
+
+    assert_eq!(report.imported, 1);
+    assert!(price > 610050 && price < 800000, "{price}");
+    assert!(matches!(
+        report.errors[..],
+        [
+            (3, WatchError::ParseField { field: "date", .. }),
+            (4, WatchError::ParseField { field: "price", .. })
+        ]
+    ));
+
+    // Geneva and Hong Kong sales are in currencies without a symbol of ours
+    let csv = "Date,Title,Price,Currency
+2023-05-13,Rolex Daytona 116500LN,30000,CHF
+2023-05-13,Rolex Daytona 116500LN,200000,HKD
+";
+    let mut data = PriceDatabase::new("ImportTest");
+    let report = import_csv(csv.as_bytes(), &ImportOptions::default(), &mut data).unwrap();
+
+    assert_eq!(report.imported, 2, "{:?}", report.errors);
+    // a franc was worth more than a dollar, a Hong Kong dollar much less
+    assert!(data.entries[0]
+        .price
+        .is_some_and(|p| p > 3000000 && p < 4000000));
+    assert!(data.entries[1]
+        .price
+        .is_some_and(|p| p > 2000000 && p < 3000000));
+
+    // rows without a reference are kept for review, aliases name brands
+    let csv = "Date,Title,Price,Currency
+2023-05-13,AP Royal Oak full set,30000,USD
+";
+    let mut data = PriceDatabase::new("ImportTest");
+    let report = import_csv(csv.as_bytes(), &ImportOptions::default(), &mut data).unwrap();
+
+    assert_eq!(report.imported, 1, "{:?}", report.errors);
+    assert_eq!(&*data.entries[0].brand, "Audemars Piguet");
+    assert_eq!(data.entries[0].collection.as_deref(), Some("Royal Oak"));
+    assert!(data.entries[0].needs_review);
+
+    // and are left out of exports unless asked for
+    let row = serde_json::to_value(&data.entries[0]).unwrap();
+    let filter = ExportFilter {
+        kinds: Some(vec![ListingKind::ForSale]),
+        unclassified: false,
+        review: false,
+    };
+
+    assert!(!filter.keeps(&row));
+    assert!(ExportFilter {
+        review: true,
+        ..filter
+    }
+    .keeps(&row));
+
+    // a missing column fails the whole file
+    assert!(import_csv("Date,Title\n".as_bytes(), &options, &mut data).is_err());
+}
+
+#[tokio::test]
+async fn retry_policy() {
+    use crate::{http::parse_retry_after, retry::RetryPolicy};
+    use std::time::Duration;
+
+    let status = |status, retry_after| WatchError::HttpStatus {
+        url: String::new(),
+        status,
+        retry_after,
+    };
+
+    assert!(status(503, None).is_retryable());
+    assert!(status(429, None).is_retryable());
+    assert!(!status(404, None).is_retryable());
+    assert!(!WatchError::MissingElement("#inlinemodform".to_owned()).is_retryable());
+
+    assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(Duration::ZERO)
+    );
+
+    let policy = RetryPolicy {
+        attempts: 3,
+        base_delay_ms: 1,
+        max_delay_secs: 1,
+    };
+
+    assert_eq!(
+        policy.delay(3, &status(503, None)),
+        Duration::from_millis(4)
+    );
+    assert_eq!(
+        policy.delay(1, &status(429, Some(Duration::from_secs(60)))),
+        Duration::from_secs(1)
+    );
+
+    let mut calls = 0;
+    let result = policy
+        .run(|| {
+            calls += 1;
+            let failed = calls < 3;
+            async move {
+                match failed {
+                    true => Err(status(503, None)),
+                    false => Ok(calls),
+                }
+            }
+        })
+        .await;
+
+    assert_eq!(result.unwrap(), 3);
+
+    // permanent errors are not retried
+    let mut calls = 0;
+    let result: Result<()> = policy
+        .run(|| {
+            calls += 1;
+            async { Err(status(404, None)) }
+        })
+        .await;
+
+    assert!(result.is_err());
+    assert_eq!(calls, 1);
+}
+
+/// Minimal vBulletin stand-in: the forum page shows a logout link only to
+/// requests carrying the cookie set by a successful login
+async fn vbulletin_stub(logins: std::sync::Arc<std::sync::atomic::AtomicUsize>) -> String {
+    use std::sync::atomic::Ordering;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+
+            // read the head and a body up to its content length
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+
+                let s = String::from_utf8_lossy(&request).to_lowercase();
+
+                if let Some(head_end) = s.find("\r\n\r\n") {
+                    let length = s
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:"))
+                        .map_or(0, |l| l.trim().parse().unwrap());
+
+                    if request.len() >= head_end + 4 + length || n == 0 {
+                        break;
+                    }
+                }
+            }
+
+            let request = String::from_utf8_lossy(&request).into_owned();
+
+            let (cookie, body) = if request.starts_with("POST /login.php?do=login") {
+                if request.contains("vb_login_username=alice")
+                    && request.contains("vb_login_password=secret")
+                {
+                    logins.fetch_add(1, Ordering::SeqCst);
+
+                    (
+                        "Set-Cookie: bbuserid=7; Path=/; Max-Age=3600\r\n",
+                        "Thank you for logging in, alice.",
+                    )
+                } else {
+                    ("", "You have entered an invalid username or password.")
+                }
+            } else if request.contains("bbuserid=7") {
+                ("", "<a href=\"login.php?do=logout\">Log Out</a> prices")
+            } else {
+                (
+                    "",
+                    "<form action=\"login.php?do=login\">You are not logged in</form>",
+                )
+            };
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\n{cookie}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn vbulletin_session() {
+    use crate::{
+        fetch::Fetcher,
+        http::{HttpClient, Politeness},
+        session::{Login, VBulletinSession},
+    };
+    use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc};
+
+    let logins = Arc::new(AtomicUsize::new(0));
+    let base_url = vbulletin_stub(logins.clone()).await;
+    let http = HttpClient::new(Politeness {
+        requests_per_second: 1000.0,
+        min_delay_ms: 0,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let dir = std::env::temp_dir().join(format!("watchinspect-cookies-{}", std::process::id()));
+    let cookies_path = dir.join("forum.json");
+    let login = Login {
+        username: "alice".to_owned(),
+        password: Some("secret".to_owned()),
+        password_env: None,
+    };
+    let url = format!("{base_url}/forumdisplay.php?f=9");
+
+    // a guest page triggers a login and refetch
+    let session = VBulletinSession::new(&http, &base_url, login.clone(), &cookies_path).unwrap();
+
+    assert!(session.fetch(&url).await.unwrap().contains("prices"));
+    assert_eq!(logins.load(Ordering::SeqCst), 1);
+    assert!(std::fs::read_to_string(&cookies_path)
+        .unwrap()
+        .contains("bbuserid"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(&cookies_path)
+            .unwrap()
+            .permissions()
+            .mode();
+
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // the saved cookies are reused without logging in again
+    let session = VBulletinSession::new(&http, &base_url, login.clone(), &cookies_path).unwrap();
+
+    assert!(session.fetch(&url).await.unwrap().contains("prices"));
+    assert_eq!(logins.load(Ordering::SeqCst), 1);
+
+    // wrong credentials fail instead of scraping guest pages
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let wrong = Login {
+        password: Some("wrong".to_owned()),
+        ..login
+    };
+    let session = VBulletinSession::new(&http, &base_url, wrong, &cookies_path).unwrap();
+
+    assert!(matches!(
+        session.fetch(&url).await,
+        Err(WatchError::LoginFailed(_))
+    ));
+}